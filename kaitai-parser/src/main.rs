@@ -2,6 +2,7 @@ use kaitai_rs::core::kaitai_struct::KaitaiStruct;
 use kaitai_rs::ks_language::format_description::FormatDescription;
 use std::env;
 use std::path::Path;
+use std::process;
 
 fn main() {
     // Get command-line arguments
@@ -19,13 +20,22 @@ fn main() {
 
     // Load the format description from the YAML file
     let format_description =
-        FormatDescription::load_from_file(Path::new(format_description_path)).unwrap();
+        match FormatDescription::load_from_file(Path::new(format_description_path)) {
+            Ok(format_description) => format_description,
+            Err(error) => {
+                eprintln!("Cannot load {}: {}", format_description_path, error);
+                process::exit(1);
+            }
+        };
 
     // Create a new parser with the loaded format description
     let mut parser = KaitaiStruct::new(format_description);
 
     // Parse the file
-    parser.parse_file(Path::new(file_path)).unwrap();
+    if let Err(error) = parser.parse_file(Path::new(file_path)) {
+        eprintln!("Cannot parse {}: {}", file_path, error);
+        process::exit(1);
+    }
 
     // Print the AST
     parser.ast.print_ast();
@@ -39,8 +39,9 @@ impl Config {
     pub const TYPE_NAME_PATTERN: &'static str =
         concat!(r"^([a-z][a-z0-9_]*::)*[a-z][a-z0-9_]*(\(.+\))?$");
 
-    // Regular expression pattern for processes
-    pub const PROCESS_PATTERN: &'static str = concat!(r"^zlib|(xor|rol|ror)\(.*\)$");
+    // Regular expression pattern for processes: a built-in or custom routine name, possibly followed by arguments
+    pub const PROCESS_PATTERN: &'static str =
+        concat!(r"^([a-z][a-z0-9_]*\.)*[a-z][a-z0-9_]*(\(.*\))?$");
 
     // Regular expression pattern for DocRef
     pub const DOCREF_PATTERN: &'static str = concat!(
@@ -1,3 +1,7 @@
+use crate::ks_language::language::kaitai_type::{
+    parse_float, parse_signed_integer, parse_unsigned_integer,
+};
+use crate::ks_language::language::meta::EndianEnum;
 use colored::*;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -9,7 +13,23 @@ pub type NodeRef = Rc<RefCell<Node>>;
 pub enum NodeType {
     String,
     Integer,
+    SignedInteger,
+    Float,
+    Boolean,
     Array,
+    Struct,
+    List,
+    Absent,
+}
+
+/// The enum the integer value of a node is an instance of
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumMember {
+    /// The path of the enum, qualified by the types defining it (`body::kind`)
+    pub enum_name: String,
+
+    /// The name of the member matching the value, `None` if the value is not a member of the enum
+    pub member: Option<String>,
 }
 
 /// A struct representing a node in an Abstract Syntax Tree (AST)
@@ -29,6 +49,18 @@ pub struct Node {
 
     /// The type of this node in the AST
     node_type: Option<NodeType>,
+
+    /// The endianness of the data associated with this node, if it is numeric
+    endian: Option<EndianEnum>,
+
+    /// The start and end positions of the bytes read for this node in its stream, if any
+    span: Option<(usize, usize)>,
+
+    /// The enum the integer value of this node is an instance of, if any
+    enum_member: Option<EnumMember>,
+
+    /// The text decoded from the data of this node, if it is a string
+    string: Option<String>,
 }
 
 impl Node {
@@ -40,6 +72,10 @@ impl Node {
             children: Vec::new(),
             data: None,
             node_type: None,
+            endian: None,
+            span: None,
+            enum_member: None,
+            string: None,
         }))
     }
 
@@ -74,11 +110,25 @@ impl Node {
         self.children.push(child);
     }
 
+    /// Removes the direct children of this node with the given ID
+    pub fn remove_child(&mut self, id: &str) {
+        self.children
+            .retain(|child| child.borrow().get_id().as_deref() != Some(id));
+    }
+
     /// Gets the children of this node in the AST, if any
     pub fn get_children(&self) -> &Vec<NodeRef> {
         &self.children
     }
 
+    /// Gets the direct child of this node with the given ID, if any
+    pub fn get_child(&self, id: &str) -> Option<NodeRef> {
+        self.children
+            .iter()
+            .find(|child| child.borrow().get_id().as_deref() == Some(id))
+            .cloned()
+    }
+
     /// Gets the ID of this node in the AST
     pub fn get_id(&self) -> &Option<String> {
         &self.id
@@ -94,6 +144,88 @@ impl Node {
         self.node_type.as_ref()
     }
 
+    /// Sets the endianness of the data associated with this node
+    pub fn set_endian(&mut self, endian: EndianEnum) {
+        self.endian = Some(endian);
+    }
+
+    /// Gets the endianness of the data associated with this node, if any
+    pub fn get_endian(&self) -> Option<EndianEnum> {
+        self.endian
+    }
+
+    /// Sets the start and end positions of the bytes read for this node in its stream
+    pub fn set_span(&mut self, start: usize, end: usize) {
+        self.span = Some((start, end));
+    }
+
+    /// Gets the start and end positions of the bytes read for this node in its stream, if any
+    pub fn get_span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
+
+    /// Sets the enum the integer value of this node is an instance of
+    pub fn set_enum_member(&mut self, enum_member: EnumMember) {
+        self.enum_member = Some(enum_member);
+    }
+
+    /// Gets the enum the integer value of this node is an instance of, if any
+    pub fn get_enum_member(&self) -> Option<&EnumMember> {
+        self.enum_member.as_ref()
+    }
+
+    /// Sets the text decoded from the data of this node
+    pub fn set_string(&mut self, string: String) {
+        self.string = Some(string);
+    }
+
+    /// Gets the text decoded from the data of this node, if it is a string
+    pub fn get_string(&self) -> Option<&str> {
+        self.string.as_deref()
+    }
+
+    /// Interprets the data of this node as an unsigned integer, if possible
+    /// Data without an endianness is interpreted as little-endian
+    pub fn get_unsigned_integer(&self) -> Option<u64> {
+        let data = self.data.as_ref()?;
+        match data.len() {
+            1..=8 => Some(parse_unsigned_integer(
+                data,
+                data.len(),
+                self.endian.unwrap_or(EndianEnum::Le),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Interprets the data of this node as a two's complement signed integer, if possible
+    /// Data without an endianness is interpreted as little-endian
+    pub fn get_signed_integer(&self) -> Option<i64> {
+        let data = self.data.as_ref()?;
+        match data.len() {
+            1..=8 => Some(parse_signed_integer(
+                data,
+                data.len(),
+                self.endian.unwrap_or(EndianEnum::Le),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Interprets the data of this node as an IEEE-754 floating-point number, if possible
+    /// Data without an endianness is interpreted as little-endian
+    pub fn get_float(&self) -> Option<f64> {
+        let data = self.data.as_ref()?;
+        match data.len() {
+            4 | 8 => Some(parse_float(
+                data,
+                data.len(),
+                self.endian.unwrap_or(EndianEnum::Le),
+            )),
+            _ => None,
+        }
+    }
+
     /// Recursively gets data from the children of this node in the AST
     pub fn get_data_from_children(&self) -> Vec<Vec<u8>> {
         let mut data = Vec::new();
@@ -116,6 +248,10 @@ impl Clone for Node {
             children: self.children.iter().cloned().collect(),
             data: self.data.clone(),
             node_type: self.node_type.clone(),
+            endian: self.endian,
+            span: self.span,
+            enum_member: self.enum_member.clone(),
+            string: self.string.clone(),
         }
     }
 }
@@ -203,6 +339,20 @@ impl AST {
         self.print_node(self.get_root(), 0, 0);
     }
 
+    /// Helper function to format an enum value as its member name followed by the hexadecimal integer
+    /// Values that are not members of the enum are flagged in red
+    fn format_enum(&self, enum_member: &EnumMember, sign: &str, magnitude: u64) -> String {
+        match &enum_member.member {
+            Some(member) => format!("{} ({}0x{:x})", member, sign, magnitude),
+            None => format!(
+                "{}0x{:x} {}",
+                sign,
+                magnitude,
+                format!("// unknown {} value", enum_member.enum_name).bright_red()
+            ),
+        }
+    }
+
     /// Helper function to print a node and its children recursively with colored node names
     fn print_node(&self, node: &NodeRef, level: usize, index: usize) {
         let node_borrowed = node.borrow();
@@ -212,13 +362,17 @@ impl AST {
             .unwrap_or_else(|| format!("{}", index))
             // Color the node name or index in green
             .bright_green();
-    
+
         if node_borrowed.get_children().is_empty() {
             // Print the node name or index with the appropriate indentation and its data
             let data = match (&node_borrowed.data, node_borrowed.get_node_type()) {
-                 // String
+                // String, decoded with its encoding when read by the engine
+                (Some(_), Some(NodeType::String)) if node_borrowed.get_string().is_some() => {
+                    format!("{:?}", node_borrowed.get_string().unwrap_or_default())
+                }
                 (Some(d), Some(NodeType::String)) => {
-                    let filtered_string: String = d.iter()
+                    let filtered_string: String = d
+                        .iter()
                         // Remove null bytes
                         .filter(|&&byte| byte != 0)
                         .map(|&byte| byte as char)
@@ -227,12 +381,9 @@ impl AST {
                 }
                 // Integer
                 (Some(d), Some(NodeType::Integer)) => {
-                    let integer = match d.len() {
-                        1 => u8::from_le_bytes(d[..1].try_into().unwrap()) as u64,
-                        2 => u16::from_le_bytes(d[..2].try_into().unwrap()) as u64,
-                        4 => u32::from_le_bytes(d[..4].try_into().unwrap()) as u64,
-                        8 => u64::from_le_bytes(d[..8].try_into().unwrap()) as u64,
-                        _ => {
+                    let integer = match node_borrowed.get_unsigned_integer() {
+                        Some(integer) => integer,
+                        None => {
                             eprintln!("Invalid integer data: {:?}", d);
                             return;
                         }
@@ -240,10 +391,50 @@ impl AST {
                     // Color the decimal comment in grey
                     let decimal = format!("// {}", integer).bright_black();
 
-                    // Format the integer
-                    format!("0x{:x} {}", integer, decimal)
+                    match node_borrowed.get_enum_member() {
+                        Some(enum_member) => self.format_enum(enum_member, "", integer),
+                        // Format the integer
+                        None => format!("0x{:x} {}", integer, decimal),
+                    }
+                }
+                // Signed integer
+                (Some(d), Some(NodeType::SignedInteger)) => {
+                    let integer = match node_borrowed.get_signed_integer() {
+                        Some(integer) => integer,
+                        None => {
+                            eprintln!("Invalid signed integer data: {:?}", d);
+                            return;
+                        }
+                    };
+                    // Color the decimal comment in grey
+                    let decimal = format!("// {}", integer).bright_black();
+
+                    // Format the integer, keeping the sign in front of the hexadecimal magnitude
+                    let sign = if integer < 0 { "-" } else { "" };
+                    match node_borrowed.get_enum_member() {
+                        Some(enum_member) => {
+                            self.format_enum(enum_member, sign, integer.unsigned_abs())
+                        }
+                        None => format!("{}0x{:x} {}", sign, integer.unsigned_abs(), decimal),
+                    }
+                }
+                // Float
+                (Some(d), Some(NodeType::Float)) => match node_borrowed.get_float() {
+                    Some(float) => format!("{:?}", float),
+                    None => {
+                        eprintln!("Invalid float data: {:?}", d);
+                        return;
+                    }
+                },
+                // Boolean
+                (Some(d), Some(NodeType::Boolean)) => {
+                    format!("{}", d.iter().any(|&byte| byte != 0))
                 }
                 (Some(d), _) => format!("{:?}", d),
+                // Empty repetition
+                (None, Some(NodeType::List)) => "[]".to_string(),
+                // Conditional attribute that was not read
+                (None, Some(NodeType::Absent)) => "absent".bright_black().to_string(),
                 (None, _) => "None".to_string(),
             };
             println!("{}{}: {}", " ".repeat(level * 4), id_or_index, data);
@@ -0,0 +1,87 @@
+use crate::core::error::DecodingError;
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE};
+
+// The characters of the bytes 0x80 to 0xff in code page 437, the lower half is ASCII
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/// Decodes the bytes of a string with the given encoding, named as in the `encoding` keys
+///
+/// Invalid sequences are an error, unless `lossy` is set: they are then replaced by U+FFFD.
+/// Besides UTF-8, UTF-16LE/BE, ASCII, ISO-8859-1 to 16, CP437 and Shift-JIS, the labels
+/// known to the WHATWG Encoding Standard are accepted, e.g. `windows-1251`
+pub fn decode(bytes: &[u8], encoding: &str, lossy: bool) -> Result<String, DecodingError> {
+    let invalid = || DecodingError::InvalidBytes {
+        encoding: encoding.to_string(),
+        bytes: bytes.to_vec(),
+    };
+
+    match normalize(encoding).as_str() {
+        "UTF-8" | "UTF8" if lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        "UTF-8" | "UTF8" => String::from_utf8(bytes.to_vec()).map_err(|_| invalid()),
+        "ASCII" | "US-ASCII" if lossy || bytes.is_ascii() => Ok(bytes
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii() {
+                    byte as char
+                } else {
+                    '\u{fffd}'
+                }
+            })
+            .collect()),
+        "ASCII" | "US-ASCII" => Err(invalid()),
+        // The WHATWG labels map ISO-8859-1 to windows-1252, every byte is its own code point here
+        "ISO-8859-1" | "ISO8859-1" | "LATIN1" => {
+            Ok(bytes.iter().map(|&byte| byte as char).collect())
+        }
+        "CP437" | "IBM437" => Ok(bytes
+            .iter()
+            .map(|&byte| match byte {
+                0x00..=0x7f => byte as char,
+                _ => CP437_HIGH
+                    .chars()
+                    .nth(byte as usize - 0x80)
+                    .unwrap_or('\u{fffd}'),
+            })
+            .collect()),
+        "UTF-16LE" => decode_with(UTF_16LE, bytes, lossy).ok_or_else(invalid),
+        "UTF-16BE" => decode_with(UTF_16BE, bytes, lossy).ok_or_else(invalid),
+        "SHIFT-JIS" | "SJIS" => decode_with(SHIFT_JIS, bytes, lossy).ok_or_else(invalid),
+        label => match Encoding::for_label(label.as_bytes()) {
+            Some(encoding_instance) => {
+                decode_with(encoding_instance, bytes, lossy).ok_or_else(invalid)
+            }
+            None => Err(DecodingError::UnknownEncoding(encoding.to_string())),
+        },
+    }
+}
+
+/// Encodes a terminator byte as a code unit of an encoding, the terminator of strings in that encoding
+///
+/// UTF-16 terminators are two bytes long, in the byte order of the encoding: a `terminator: 10`
+/// newline is `0a 00` in UTF-16LE. Strings in any other supported encoding end with the byte itself
+pub fn encode_terminator(terminator: u8, encoding: &str) -> Vec<u8> {
+    match normalize(encoding).as_str() {
+        "UTF-16LE" => vec![terminator, 0],
+        "UTF-16BE" => vec![0, terminator],
+        _ => vec![terminator],
+    }
+}
+
+// Normalizes the name of an encoding, names are case-insensitive and `_` is accepted for `-`
+fn normalize(encoding: &str) -> String {
+    encoding.trim().to_uppercase().replace('_', "-")
+}
+
+// Decodes bytes with an encoding of the Encoding Standard, without any byte order mark handling
+// Returns `None` if the bytes are malformed and the decoding is not lossy
+fn decode_with(encoding: &'static Encoding, bytes: &[u8], lossy: bool) -> Option<String> {
+    if lossy {
+        Some(encoding.decode_without_bom_handling(bytes).0.into_owned())
+    } else {
+        encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|string| string.into_owned())
+    }
+}
@@ -0,0 +1,459 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Error raised when the bytes read for a `contents` attribute differ from the expected ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentsMismatchError {
+    /// The offset in the stream where the contents start
+    pub offset: usize,
+
+    /// The bytes expected by the format description
+    pub expected: Vec<u8>,
+
+    /// The bytes actually read from the stream
+    pub actual: Vec<u8>,
+}
+
+impl fmt::Display for ContentsMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Contents mismatch at offset {}: expected {:02x?}, got {:02x?}",
+            self.offset, self.expected, self.actual
+        )
+    }
+}
+
+impl Error for ContentsMismatchError {}
+
+/// Error raised when a read goes past the end of the stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndOfStreamError {
+    /// The offset in the stream where the read starts
+    pub offset: usize,
+
+    /// The number of bytes requested, `None` when reading up to a terminator that is not found
+    pub requested: Option<usize>,
+
+    /// The number of bytes left in the stream from the offset
+    pub remaining: usize,
+}
+
+impl fmt::Display for EndOfStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.requested {
+            Some(requested) => write!(
+                f,
+                "Unexpected end of stream: requested {} bytes at offset {}, only {} remaining",
+                requested, self.offset, self.remaining
+            ),
+            None => write!(
+                f,
+                "Unexpected end of stream: terminator not found in the {} bytes remaining at offset {}",
+                self.remaining, self.offset
+            ),
+        }
+    }
+}
+
+impl Error for EndOfStreamError {}
+
+/// Error raised when an expression cannot be parsed or evaluated
+#[derive(Debug, PartialEq)]
+pub enum ExpressionError {
+    /// The expression does not follow the grammar of the expression language
+    Syntax { expr: String, message: String },
+
+    /// A name used in the expression cannot be resolved
+    UnknownName(String),
+
+    /// An attribute used in the expression was skipped or is not parsed yet
+    Absent(String),
+
+    /// An operator or a method is applied to a value of the wrong type
+    TypeMismatch(String),
+
+    /// An integer division or modulo by zero
+    DivisionByZero,
+
+    /// An integer result does not fit in 64 bits
+    Overflow,
+
+    /// An index is out of the bounds of the indexed value
+    IndexOutOfBounds { index: i128, length: usize },
+
+    /// The expression uses a construct the evaluator does not support
+    Unsupported(String),
+
+    /// An instance used in the expression cannot be computed
+    Instance { name: String, error: InstanceError },
+}
+
+/// The error of the parsing of an instance used by an expression
+///
+/// The error is reported as is by the parsing, located from the struct of the instance
+/// rather than from the attribute using the instance
+#[derive(Debug)]
+pub struct InstanceError {
+    /// The error of the instance
+    pub error: Box<KaitaiError>,
+
+    // The position of the struct of the instance in the structs being parsed, where the error is reported
+    pub(crate) depth: usize,
+}
+
+// The errors of the parsing cannot be compared, instance errors are compared by their message
+impl PartialEq for InstanceError {
+    fn eq(&self, other: &Self) -> bool {
+        self.depth == other.depth && self.error.to_string() == other.error.to_string()
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::Syntax { expr, message } => {
+                write!(f, "Invalid expression '{}': {}", expr, message)
+            }
+            ExpressionError::UnknownName(name) => write!(f, "Unknown name '{}'", name),
+            ExpressionError::Absent(name) => {
+                write!(f, "Attribute '{}' is absent or not parsed yet", name)
+            }
+            ExpressionError::TypeMismatch(message) => write!(f, "Type mismatch: {}", message),
+            ExpressionError::DivisionByZero => write!(f, "Division by zero"),
+            ExpressionError::Overflow => write!(f, "Integer overflow"),
+            ExpressionError::IndexOutOfBounds { index, length } => write!(
+                f,
+                "Index {} out of bounds for a length of {}",
+                index, length
+            ),
+            ExpressionError::Unsupported(message) => {
+                write!(f, "Unsupported expression: {}", message)
+            }
+            ExpressionError::Instance { name, error } => {
+                write!(f, "Cannot compute instance '{}': {}", name, error.error)
+            }
+        }
+    }
+}
+
+impl Error for ExpressionError {}
+
+impl From<ExpressionError> for io::Error {
+    fn from(error: ExpressionError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Error raised when the bytes of a string cannot be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodingError {
+    /// The encoding is not supported
+    UnknownEncoding(String),
+
+    /// The bytes are not a valid sequence in the encoding
+    InvalidBytes { encoding: String, bytes: Vec<u8> },
+}
+
+impl fmt::Display for DecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodingError::UnknownEncoding(encoding) => {
+                write!(f, "Unknown encoding '{}'", encoding)
+            }
+            DecodingError::InvalidBytes { encoding, bytes } => {
+                write!(f, "Invalid {} bytes {:02x?}", encoding, bytes)
+            }
+        }
+    }
+}
+
+impl Error for DecodingError {}
+
+impl From<DecodingError> for io::Error {
+    fn from(error: DecodingError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Error raised while loading a format description or parsing data with it
+///
+/// Errors are located by a path and an offset. In a format description, the path is the path of
+/// the YAML node (`/types/header/seq/0/size`). In parsed data, it is the path of the attribute in
+/// the AST (`header.entries[2].name`) and the offset is the position of the attribute in its stream.
+#[derive(Debug)]
+pub enum KaitaiError {
+    /// A file cannot be read
+    Io(io::Error),
+
+    /// The format description is not valid YAML
+    Yaml(serde_yaml::Error),
+
+    /// The format description is not valid JSON
+    Json(serde_json::Error),
+
+    /// The format description does not follow the Kaitai Struct schema
+    ///
+    /// Inconsistencies only found while parsing, e.g. an unknown type, are located by the attribute
+    Schema { path: String, message: String },
+
+    /// An expression cannot be evaluated while parsing
+    Expression {
+        path: String,
+        offset: Option<usize>,
+        error: ExpressionError,
+    },
+
+    /// The stream ends before the data of an attribute
+    EndOfStream {
+        path: String,
+        error: EndOfStreamError,
+    },
+
+    /// The data does not match the format description, e.g. an invalid string or compressed payload
+    Validation {
+        path: String,
+        offset: Option<usize>,
+        message: String,
+    },
+
+    /// The bytes of a `contents` attribute differ from the expected ones
+    ContentsMismatch {
+        path: String,
+        error: ContentsMismatchError,
+    },
+
+    /// The format description uses a feature that is not supported
+    Unsupported {
+        path: String,
+        offset: Option<usize>,
+        message: String,
+    },
+}
+
+impl KaitaiError {
+    /// Gets the path of the YAML node or of the attribute where the error occurred, if known
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            KaitaiError::Io(_) | KaitaiError::Yaml(_) | KaitaiError::Json(_) => None,
+            KaitaiError::Schema { path, .. }
+            | KaitaiError::Expression { path, .. }
+            | KaitaiError::EndOfStream { path, .. }
+            | KaitaiError::Validation { path, .. }
+            | KaitaiError::ContentsMismatch { path, .. }
+            | KaitaiError::Unsupported { path, .. } => Some(path),
+        }
+    }
+
+    /// Gets the offset in the stream of the attribute where the error occurred, if known
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            KaitaiError::Io(_)
+            | KaitaiError::Yaml(_)
+            | KaitaiError::Json(_)
+            | KaitaiError::Schema { .. } => None,
+            KaitaiError::ContentsMismatch { error, .. } => Some(error.offset),
+            KaitaiError::EndOfStream { error, .. } => Some(error.offset),
+            KaitaiError::Expression { offset, .. }
+            | KaitaiError::Validation { offset, .. }
+            | KaitaiError::Unsupported { offset, .. } => *offset,
+        }
+    }
+
+    /// Creates a schema error, located when it reaches the attribute or the YAML node being parsed
+    pub fn schema(message: impl Into<String>) -> Self {
+        KaitaiError::Schema {
+            path: String::new(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates a validation error, located when it reaches the attribute being parsed
+    pub fn validation(message: impl Into<String>) -> Self {
+        KaitaiError::Validation {
+            path: String::new(),
+            offset: None,
+            message: message.into(),
+        }
+    }
+
+    /// Creates an error for an unsupported feature, located when it reaches the attribute being parsed
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        KaitaiError::Unsupported {
+            path: String::new(),
+            offset: None,
+            message: message.into(),
+        }
+    }
+
+    /// Locates the error in the given attribute, or element of a repeated attribute (`[index]`)
+    ///
+    /// The errors are located from the innermost attribute outwards: the name is prepended to the
+    /// path, and the offset is the one of the innermost attribute having one
+    pub fn in_attribute(mut self, name: &str, attribute_offset: Option<usize>) -> Self {
+        if let Some(path) = self.path_mut() {
+            *path = match path.as_str() {
+                "" => name.to_string(),
+                inner if inner.starts_with('[') => format!("{}{}", name, inner),
+                inner => format!("{}.{}", name, inner),
+            };
+        }
+        match &mut self {
+            KaitaiError::Expression { offset, .. }
+            | KaitaiError::Validation { offset, .. }
+            | KaitaiError::Unsupported { offset, .. } => {
+                *offset = offset.or(attribute_offset);
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Locates the error in the given key of a YAML node, prepending it to the path
+    pub fn in_schema(mut self, key: &str) -> Self {
+        if let Some(path) = self.path_mut() {
+            *path = format!("/{}{}", key, path);
+        }
+        self
+    }
+
+    // Gets the path of the error for locating it, if it has one
+    fn path_mut(&mut self) -> Option<&mut String> {
+        match self {
+            KaitaiError::Io(_) | KaitaiError::Yaml(_) | KaitaiError::Json(_) => None,
+            KaitaiError::Schema { path, .. }
+            | KaitaiError::Expression { path, .. }
+            | KaitaiError::EndOfStream { path, .. }
+            | KaitaiError::Validation { path, .. }
+            | KaitaiError::ContentsMismatch { path, .. }
+            | KaitaiError::Unsupported { path, .. } => Some(path),
+        }
+    }
+}
+
+impl fmt::Display for KaitaiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KaitaiError::Io(error) => write!(f, "{}", error),
+            KaitaiError::Yaml(error) => write!(f, "Invalid YAML: {}", error),
+            KaitaiError::Json(error) => write!(f, "Invalid JSON: {}", error),
+            KaitaiError::Schema { path, message } if path.is_empty() => {
+                write!(f, "Invalid format description: {}", message)
+            }
+            KaitaiError::Schema { path, message } => {
+                write!(f, "Invalid format description at '{}': {}", path, message)
+            }
+            KaitaiError::Expression { error, .. } => write!(f, "{}", error),
+            KaitaiError::EndOfStream { error, .. } => write!(f, "{}", error),
+            KaitaiError::Validation { message, .. } | KaitaiError::Unsupported { message, .. } => {
+                write!(f, "{}", message)
+            }
+            KaitaiError::ContentsMismatch { error, .. } => write!(f, "{}", error),
+        }?;
+
+        // Errors in parsed data are followed by their location, unless their message gives it
+        match (self, self.path(), self.offset()) {
+            (KaitaiError::Schema { .. }, _, _) => Ok(()),
+            (
+                KaitaiError::EndOfStream { path, .. } | KaitaiError::ContentsMismatch { path, .. },
+                _,
+                _,
+            ) if !path.is_empty() => write!(f, " (attribute '{}')", path),
+            (_, Some(path), Some(offset)) if !path.is_empty() => {
+                write!(f, " (attribute '{}', offset {})", path, offset)
+            }
+            (_, Some(path), None) if !path.is_empty() => write!(f, " (attribute '{}')", path),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Error for KaitaiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KaitaiError::Io(error) => Some(error),
+            KaitaiError::Yaml(error) => Some(error),
+            KaitaiError::Json(error) => Some(error),
+            KaitaiError::Expression { error, .. } => Some(error),
+            KaitaiError::EndOfStream { error, .. } => Some(error),
+            KaitaiError::ContentsMismatch { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Errors of the loader and of the stream are reported as I/O errors: invalid data in a format
+/// description is a schema error, and the stream wraps an `EndOfStreamError` in its errors
+impl From<io::Error> for KaitaiError {
+    fn from(error: io::Error) -> Self {
+        // Errors of this crate converted to `io::Error`, e.g. by the parsers of the format description
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<KaitaiError>())
+        {
+            let inner = error
+                .into_inner()
+                .and_then(|inner| inner.downcast::<KaitaiError>().ok());
+            return *inner.expect("the inner error is a KaitaiError");
+        }
+
+        let end_of_stream = error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<EndOfStreamError>());
+        if let Some(end_of_stream) = end_of_stream {
+            return KaitaiError::EndOfStream {
+                path: String::new(),
+                error: end_of_stream.clone(),
+            };
+        }
+
+        match error.kind() {
+            io::ErrorKind::InvalidData => KaitaiError::Schema {
+                path: String::new(),
+                message: error.to_string(),
+            },
+            _ => KaitaiError::Io(error),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for KaitaiError {
+    fn from(error: serde_yaml::Error) -> Self {
+        KaitaiError::Yaml(error)
+    }
+}
+
+impl From<serde_json::Error> for KaitaiError {
+    fn from(error: serde_json::Error) -> Self {
+        KaitaiError::Json(error)
+    }
+}
+
+impl From<ExpressionError> for KaitaiError {
+    fn from(error: ExpressionError) -> Self {
+        KaitaiError::Expression {
+            path: String::new(),
+            offset: None,
+            error,
+        }
+    }
+}
+
+impl From<DecodingError> for KaitaiError {
+    fn from(error: DecodingError) -> Self {
+        KaitaiError::validation(error.to_string())
+    }
+}
+
+impl From<KaitaiError> for io::Error {
+    fn from(error: KaitaiError) -> Self {
+        let kind = match &error {
+            KaitaiError::Io(error) => error.kind(),
+            KaitaiError::EndOfStream { .. } => io::ErrorKind::UnexpectedEof,
+            KaitaiError::Unsupported { .. } => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
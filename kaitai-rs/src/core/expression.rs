@@ -1,71 +1,992 @@
+use crate::core::ast::NodeRef;
+use crate::core::ast::NodeType;
 use crate::core::ast::AST;
+use crate::core::error::ExpressionError;
+use crate::core::stream::KaitaiStream;
+use crate::core::value::Value;
+use pest::iterators::{Pair, Pairs};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest::Parser;
 use pest_derive::Parser;
-use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::rc::Rc;
+use std::sync::OnceLock;
 
 #[derive(Parser)]
 #[grammar = "./core/expr.pest"]
 struct ExprParser;
 
-/// Converts the first 4 bytes of a Vec<u8> to an i32
-fn vec_u8_to_i32(vec: &Vec<u8>) -> Result<i32, String> {
-    if vec.len() < 4 {
-        return Err(String::from("The vector must contain at least 4 elements"));
+/// Unary operators of the expression language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Negate,
+    BitwiseNot,
+    Not,
+}
+
+/// Binary operators of the expression language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    ShiftLeft,
+    ShiftRight,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    /// Gets the symbol of the operator, used in error messages
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+        }
+    }
+}
+
+/// A node of the tree of a parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A literal value: integer, float, boolean, string or array
+    Literal(Value),
+    /// A member of an enum (`enum_name::member`)
+    EnumMember { enum_name: String, member: String },
+    /// A name: an attribute of the current type or a built-in (`_root`, `_parent`, `_io`, `_index`, `_`)
+    Name(String),
+    /// An attribute or a method without arguments (`object.name`)
+    Attribute { object: Box<Expr>, name: String },
+    /// A method call with arguments (`object.name(arguments)`)
+    MethodCall {
+        object: Box<Expr>,
+        name: String,
+        arguments: Vec<Expr>,
+    },
+    /// An element of an array or a byte array (`object[index]`)
+    Index { object: Box<Expr>, index: Box<Expr> },
+    /// The size in bytes of a type (`sizeof<type>`)
+    SizeOf(String),
+    /// A cast to a type (`object.as<type>`)
+    Cast {
+        object: Box<Expr>,
+        type_name: String,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// A conditional expression (`condition ? if_true : if_false`)
+    Ternary {
+        condition: Box<Expr>,
+        if_true: Box<Expr>,
+        if_false: Box<Expr>,
+    },
+}
+
+/// Gets the parser applying the precedence of the operators, from the lowest to the highest
+fn pratt_parser() -> &'static PrattParser<Rule> {
+    static PRATT_PARSER: OnceLock<PrattParser<Rule>> = OnceLock::new();
+    PRATT_PARSER.get_or_init(|| {
+        PrattParser::new()
+            .op(Op::infix(Rule::or, Assoc::Left))
+            .op(Op::infix(Rule::and, Assoc::Left))
+            .op(Op::prefix(Rule::not))
+            .op(Op::infix(Rule::equal, Assoc::Left)
+                | Op::infix(Rule::not_equal, Assoc::Left)
+                | Op::infix(Rule::less, Assoc::Left)
+                | Op::infix(Rule::less_equal, Assoc::Left)
+                | Op::infix(Rule::greater, Assoc::Left)
+                | Op::infix(Rule::greater_equal, Assoc::Left))
+            .op(Op::infix(Rule::bitwise_or, Assoc::Left))
+            .op(Op::infix(Rule::bitwise_xor, Assoc::Left))
+            .op(Op::infix(Rule::bitwise_and, Assoc::Left))
+            .op(Op::infix(Rule::shift_left, Assoc::Left)
+                | Op::infix(Rule::shift_right, Assoc::Left))
+            .op(Op::infix(Rule::add, Assoc::Left) | Op::infix(Rule::subtract, Assoc::Left))
+            .op(Op::infix(Rule::multiply, Assoc::Left)
+                | Op::infix(Rule::divide, Assoc::Left)
+                | Op::infix(Rule::modulo, Assoc::Left))
+            .op(Op::prefix(Rule::negate) | Op::prefix(Rule::bitwise_not))
+    })
+}
+
+/// Gets the innermost pair of a chain of single rules, such as `operator > arithmetic_operator > add`
+fn innermost(pair: Pair<Rule>) -> Pair<Rule> {
+    match pair.clone().into_inner().next() {
+        Some(inner) => innermost(inner),
+        None => pair,
     }
+}
+
+/// Gets the next inner pair of a pair, the grammar guarantees that it exists
+fn next_pair<'i>(pairs: &mut Pairs<'i, Rule>) -> Result<Pair<'i, Rule>, ExpressionError> {
+    pairs
+        .next()
+        .ok_or_else(|| ExpressionError::Unsupported("incomplete expression".to_string()))
+}
 
-    // Take the first 4 bytes and convert them to i32
-    let bytes: [u8; 4] = [vec[0], vec[1], vec[2], vec[3]];
-    let integer = i32::from_le_bytes(bytes);
+/// Parses an integer literal (decimal, hexadecimal, binary or octal)
+fn parse_integer(integer_str: &str) -> Result<Value, ExpressionError> {
+    let digits = integer_str.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") | Some("0X") => (16, &digits[2..]),
+        Some("0b") | Some("0B") => (2, &digits[2..]),
+        Some("0o") | Some("0O") => (8, &digits[2..]),
+        _ => (10, digits.as_str()),
+    };
 
-    Ok(integer)
+    let value = i128::from_str_radix(digits, radix).map_err(|_| ExpressionError::Overflow)?;
+    Value::from_integer(value)
 }
 
-/// Parses an identifier and returns the corresponding i32 value from the AST
-fn parse_identifier(ast: &AST, identifier: &str) -> Option<i32> {
-    // Look up the node in the AST by identifier
-    if let Some(node_ref) = ast.clone().get_node_by_id(identifier) {
-        let node = node_ref.borrow();
+/// Replaces the escape sequences of a double-quoted string literal
+fn unescape(string: &str) -> String {
+    let mut unescaped = String::with_capacity(string.len());
+    let mut chars = string.chars();
 
-        // Retrieve data from the node
-        if let Some(data) = node.get_data() {
-            // Convert data to i32 directly
-            if let Ok(value) = vec_u8_to_i32(data) {
-                return Some(value);
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    unescaped.push(c);
+                }
             }
+            Some(c) => unescaped.push(c),
+            None => {}
         }
     }
 
-    None
+    unescaped
+}
+
+/// Builds the value of a literal string, number, boolean or array
+fn build_literal_value(pair: Pair<Rule>) -> Result<Value, ExpressionError> {
+    match pair.as_rule() {
+        Rule::integer => parse_integer(pair.as_str()),
+        Rule::floating_point_number => {
+            pair.as_str()
+                .parse::<f64>()
+                .map(Value::Float)
+                .map_err(|error| ExpressionError::Syntax {
+                    expr: pair.as_str().to_string(),
+                    message: error.to_string(),
+                })
+        }
+        Rule::boolean => Ok(Value::Bool(pair.as_str() == "true")),
+        Rule::string => {
+            let inner = next_pair(&mut pair.into_inner())?;
+            match inner.as_rule() {
+                Rule::inner_double_quoted => Ok(Value::String(unescape(inner.as_str()))),
+                _ => Ok(Value::String(inner.as_str().to_string())),
+            }
+        }
+        Rule::array => {
+            let array = next_pair(&mut pair.into_inner())?;
+            array
+                .into_inner()
+                .map(build_literal_value)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array)
+        }
+        rule => Err(ExpressionError::Unsupported(format!("{:?} literal", rule))),
+    }
+}
+
+/// Builds the expression of a path element used on its own
+fn build_path_element(pair: Pair<Rule>) -> Result<Expr, ExpressionError> {
+    let inner = next_pair(&mut pair.into_inner())?;
+    match inner.as_rule() {
+        Rule::expression_operator_with_identifier => {
+            let mut inner = inner.into_inner();
+            let operator = next_pair(&mut inner)?;
+            let type_name = next_pair(&mut inner)?.as_str().to_string();
+            match operator.as_str() {
+                "sizeof" => Ok(Expr::SizeOf(type_name)),
+                operator => Err(ExpressionError::Unsupported(format!(
+                    "'{}<{}>' without a value",
+                    operator, type_name
+                ))),
+            }
+        }
+        _ => Ok(Expr::Name(inner.as_str().to_string())),
+    }
+}
+
+/// Applies a postfix operation (attribute access, method call or indexing) to an expression
+fn build_postfix(object: Expr, pair: Pair<Rule>) -> Result<Expr, ExpressionError> {
+    let object = Box::new(object);
+    let mut inner = pair.into_inner();
+    let first = next_pair(&mut inner)?;
+
+    if first.as_rule() == Rule::index {
+        let index = Box::new(build_expression(first)?);
+        return Ok(Expr::Index { object, index });
+    }
+
+    let element = next_pair(&mut first.into_inner())?;
+    if element.as_rule() == Rule::expression_operator_with_identifier {
+        let mut element_inner = element.into_inner();
+        let operator = next_pair(&mut element_inner)?;
+        let type_name = next_pair(&mut element_inner)?.as_str().to_string();
+        return match operator.as_str() {
+            "as" => Ok(Expr::Cast { object, type_name }),
+            operator => Err(ExpressionError::Unsupported(format!(
+                "'.{}<{}>'",
+                operator, type_name
+            ))),
+        };
+    }
+
+    let name = element.as_str().to_string();
+    match inner.next() {
+        Some(arguments) => Ok(Expr::MethodCall {
+            object,
+            name,
+            arguments: arguments
+                .into_inner()
+                .map(build_expression)
+                .collect::<Result<Vec<_>, _>>()?,
+        }),
+        None => Ok(Expr::Attribute { object, name }),
+    }
+}
+
+/// Builds a primary expression: an atom followed by its postfix operations
+fn build_primary(pair: Pair<Rule>) -> Result<Expr, ExpressionError> {
+    let mut inner = pair.into_inner();
+    let atom = next_pair(&mut next_pair(&mut inner)?.into_inner())?;
+
+    let mut expr = match atom.as_rule() {
+        Rule::literal => {
+            let literal = next_pair(&mut atom.into_inner())?;
+            if literal.as_rule() == Rule::r#enum {
+                let names: Vec<&str> = literal.into_inner().map(|name| name.as_str()).collect();
+                let (member, enum_path) = names
+                    .split_last()
+                    .ok_or_else(|| ExpressionError::Unsupported("empty enum".to_string()))?;
+                Expr::EnumMember {
+                    enum_name: enum_path.join("::"),
+                    member: member.to_string(),
+                }
+            } else {
+                Expr::Literal(build_literal_value(literal)?)
+            }
+        }
+        Rule::path_element => build_path_element(atom)?,
+        _ => build_expression(atom)?,
+    };
+
+    for postfix in inner {
+        expr = build_postfix(expr, postfix)?;
+    }
+
+    Ok(expr)
+}
+
+/// Builds a binary expression, applying the precedence of its operators
+fn build_binary(pairs: Pairs<Rule>) -> Result<Expr, ExpressionError> {
+    // The operators are nested in their category rules, the Pratt parser works on the operator rules themselves
+    let pairs = pairs.map(|pair| match pair.as_rule() {
+        Rule::operator | Rule::prefix_operator => innermost(pair),
+        _ => pair,
+    });
+
+    pratt_parser()
+        .map_primary(build_primary)
+        .map_prefix(|operator, operand| {
+            let operator = match operator.as_rule() {
+                Rule::negate => UnaryOperator::Negate,
+                Rule::bitwise_not => UnaryOperator::BitwiseNot,
+                _ => UnaryOperator::Not,
+            };
+            Ok(Expr::Unary {
+                operator,
+                operand: Box::new(operand?),
+            })
+        })
+        .map_infix(|left, operator, right| {
+            let operator = match operator.as_rule() {
+                Rule::add => BinaryOperator::Add,
+                Rule::subtract => BinaryOperator::Subtract,
+                Rule::multiply => BinaryOperator::Multiply,
+                Rule::divide => BinaryOperator::Divide,
+                Rule::modulo => BinaryOperator::Modulo,
+                Rule::shift_left => BinaryOperator::ShiftLeft,
+                Rule::shift_right => BinaryOperator::ShiftRight,
+                Rule::bitwise_and => BinaryOperator::BitwiseAnd,
+                Rule::bitwise_or => BinaryOperator::BitwiseOr,
+                Rule::bitwise_xor => BinaryOperator::BitwiseXor,
+                Rule::equal => BinaryOperator::Equal,
+                Rule::not_equal => BinaryOperator::NotEqual,
+                Rule::less => BinaryOperator::Less,
+                Rule::less_equal => BinaryOperator::LessEqual,
+                Rule::greater => BinaryOperator::Greater,
+                Rule::greater_equal => BinaryOperator::GreaterEqual,
+                Rule::and => BinaryOperator::And,
+                _ => BinaryOperator::Or,
+            };
+            Ok(Expr::Binary {
+                operator,
+                left: Box::new(left?),
+                right: Box::new(right?),
+            })
+        })
+        .parse(pairs)
+}
+
+/// Builds the expression tree of a parsed expression
+fn build_expression(pair: Pair<Rule>) -> Result<Expr, ExpressionError> {
+    match pair.as_rule() {
+        Rule::ternary_expression => {
+            let mut inner = pair.into_inner();
+            Ok(Expr::Ternary {
+                condition: Box::new(build_expression(next_pair(&mut inner)?)?),
+                if_true: Box::new(build_expression(next_pair(&mut inner)?)?),
+                if_false: Box::new(build_expression(next_pair(&mut inner)?)?),
+            })
+        }
+        Rule::binary_expression => build_binary(pair.into_inner()),
+        // `expression`, `full_expression` and `index` wrap a single expression
+        _ => build_expression(next_pair(&mut pair.into_inner())?),
+    }
+}
+
+/// Parses a kaitai language expression into an expression tree
+pub fn parse_expression(expr: &str) -> Result<Expr, ExpressionError> {
+    let mut pairs = ExprParser::parse(Rule::kaitai_expression, expr).map_err(|error| {
+        ExpressionError::Syntax {
+            expr: expr.to_string(),
+            message: error.variant.message().to_string(),
+        }
+    })?;
+
+    let kaitai_expression = next_pair(&mut pairs)?;
+    build_expression(next_pair(&mut kaitai_expression.into_inner())?)
+}
+
+/// Creates the error of an operator applied to values of unsupported types
+fn operand_mismatch(operator: BinaryOperator, left: &Value, right: &Value) -> ExpressionError {
+    ExpressionError::TypeMismatch(format!(
+        "cannot apply '{}' to {} {} and {} {}",
+        operator.symbol(),
+        left.type_name(),
+        left,
+        right.type_name(),
+        right
+    ))
+}
+
+/// Applies an arithmetic operator, on integers if both operands are integers and on floats otherwise
+fn numeric_operation(
+    operator: BinaryOperator,
+    left: &Value,
+    right: &Value,
+    integer_operation: impl Fn(i128, i128) -> Result<i128, ExpressionError>,
+    float_operation: impl Fn(f64, f64) -> f64,
+) -> Result<Value, ExpressionError> {
+    if let (Some(left), Some(right)) = (left.as_integer(), right.as_integer()) {
+        return Value::from_integer(integer_operation(left, right)?);
+    }
+
+    match (left.as_float(), right.as_float()) {
+        (Some(left), Some(right)) => Ok(Value::Float(float_operation(left, right))),
+        _ => Err(operand_mismatch(operator, left, right)),
+    }
+}
+
+/// Applies a bitwise operator, defined on integers only
+fn integer_operation(
+    operator: BinaryOperator,
+    left: &Value,
+    right: &Value,
+    operation: impl Fn(i128, i128) -> Option<i128>,
+) -> Result<Value, ExpressionError> {
+    match (left.as_integer(), right.as_integer()) {
+        (Some(left), Some(right)) => {
+            Value::from_integer(operation(left, right).ok_or(ExpressionError::Overflow)?)
+        }
+        _ => Err(operand_mismatch(operator, left, right)),
+    }
+}
+
+/// Applies a binary operator, other than the logical ones, to two values
+fn binary_operation(
+    operator: BinaryOperator,
+    left: &Value,
+    right: &Value,
+) -> Result<Value, ExpressionError> {
+    let overflow = |value: Option<i128>| value.ok_or(ExpressionError::Overflow);
+    let non_zero = |value: i128| {
+        if value == 0 {
+            Err(ExpressionError::DivisionByZero)
+        } else {
+            Ok(value)
+        }
+    };
+
+    match operator {
+        BinaryOperator::Add => match (left, right) {
+            (Value::String(left), Value::String(right)) => Ok(Value::String(left.clone() + right)),
+            _ => numeric_operation(
+                operator,
+                left,
+                right,
+                |l, r| overflow(l.checked_add(r)),
+                |l, r| l + r,
+            ),
+        },
+        BinaryOperator::Subtract => numeric_operation(
+            operator,
+            left,
+            right,
+            |l, r| overflow(l.checked_sub(r)),
+            |l, r| l - r,
+        ),
+        BinaryOperator::Multiply => numeric_operation(
+            operator,
+            left,
+            right,
+            |l, r| overflow(l.checked_mul(r)),
+            |l, r| l * r,
+        ),
+        // Integer division rounds towards negative infinity
+        BinaryOperator::Divide => numeric_operation(
+            operator,
+            left,
+            right,
+            |l, r| {
+                let r = non_zero(r)?;
+                let quotient = l / r;
+                if l % r != 0 && (l < 0) != (r < 0) {
+                    Ok(quotient - 1)
+                } else {
+                    Ok(quotient)
+                }
+            },
+            |l, r| l / r,
+        ),
+        // The result of a modulo is never negative
+        BinaryOperator::Modulo => numeric_operation(
+            operator,
+            left,
+            right,
+            |l, r| Ok(l.rem_euclid(non_zero(r)?)),
+            |l, r| l.rem_euclid(r),
+        ),
+        BinaryOperator::ShiftLeft => integer_operation(operator, left, right, |l, r| {
+            l.checked_shl(u32::try_from(r).ok()?)
+        }),
+        BinaryOperator::ShiftRight => integer_operation(operator, left, right, |l, r| {
+            Some(l >> u32::try_from(r).ok()?.min(127))
+        }),
+        BinaryOperator::BitwiseAnd => integer_operation(operator, left, right, |l, r| Some(l & r)),
+        BinaryOperator::BitwiseOr => integer_operation(operator, left, right, |l, r| Some(l | r)),
+        BinaryOperator::BitwiseXor => integer_operation(operator, left, right, |l, r| Some(l ^ r)),
+        BinaryOperator::Equal => left.equals(right).map(Value::Bool),
+        BinaryOperator::NotEqual => left.equals(right).map(|equal| Value::Bool(!equal)),
+        BinaryOperator::Less => left
+            .compare(right)
+            .map(|ordering| Value::Bool(ordering == Ordering::Less)),
+        BinaryOperator::LessEqual => left
+            .compare(right)
+            .map(|ordering| Value::Bool(ordering != Ordering::Greater)),
+        BinaryOperator::Greater => left
+            .compare(right)
+            .map(|ordering| Value::Bool(ordering == Ordering::Greater)),
+        BinaryOperator::GreaterEqual => left
+            .compare(right)
+            .map(|ordering| Value::Bool(ordering != Ordering::Less)),
+        BinaryOperator::And => Ok(Value::Bool(left.to_bool()? && right.to_bool()?)),
+        BinaryOperator::Or => Ok(Value::Bool(left.to_bool()? || right.to_bool()?)),
+    }
+}
+
+// Checks an index against the length of an array, a byte array or a list
+fn index_position(index: &Value, length: usize) -> Result<usize, ExpressionError> {
+    let index = index.as_integer().ok_or_else(|| {
+        ExpressionError::TypeMismatch(format!(
+            "expected an integer index, got {} {}",
+            index.type_name(),
+            index
+        ))
+    })?;
+
+    usize::try_from(index)
+        .ok()
+        .filter(|&position| position < length)
+        .ok_or(ExpressionError::IndexOutOfBounds { index, length })
+}
+
+/// The context an expression is evaluated in
+///
+/// Names are resolved from the current struct, the innermost of the structs being parsed
+pub struct Context<'a> {
+    /// The structs being parsed, from the root to the current one
+    structs: &'a [NodeRef],
+
+    /// The last element read by a repetition, referred to as `_`
+    last: Option<&'a NodeRef>,
+
+    /// The index of the element being read by a repetition, referred to as `_index`
+    index: Option<usize>,
+
+    /// The stream of the current struct, referred to as `_io`
+    io: Option<&'a KaitaiStream<'a>>,
+
+    /// The types, enums and instances of the format description
+    definitions: Option<Box<dyn Definitions + 'a>>,
+}
+
+// An operand of an index or of `size`, lists of attributes are kept as their node so that an
+// element or the size of a list is read without converting the whole list to an array
+enum Indexable {
+    List(NodeRef),
+    Value(Value),
 }
 
-/// Parses an integer from a string and returns it as i32
-fn parse_integer(integer_str: &str) -> Option<i32> {
-    integer_str.parse::<i32>().ok()
+/// The types, enums and instances of a format description, as referred to by expressions
+pub trait Definitions {
+    /// Computes the size in bytes of a type, for `sizeof<type>`
+    fn sizeof(&self, type_name: &str) -> Result<usize, ExpressionError>;
+
+    /// Gets the value of a member of an enum, for `enum_name::member`
+    fn enum_value(&self, enum_name: &str, member: &str) -> Result<Value, ExpressionError>;
+
+    /// Computes an instance of a struct, returning `None` if the struct has no such instance
+    fn instance(&self, structure: &NodeRef, name: &str)
+        -> Result<Option<NodeRef>, ExpressionError>;
 }
 
-/// Evaluates a kaitai language expression against an Abstract Syntax Tree (AST) of Vec<u8> nodes and returns an i32 result
-/// TODO: handle more complex expressions, for now we only evaluate expressions composed solely of an integer or an identifier
-pub fn evaluate(ast: &AST, expr: &str) -> i32 {
-    // Try parsing an identifier
-    if let Ok(pairs) = ExprParser::parse(Rule::identifier, expr) {
-        for pair in pairs {
-            let identifier = pair.as_str();
-            if let Some(value) = parse_identifier(ast, identifier) {
-                return value;
+impl<'a> Context<'a> {
+    /// Creates a context from the structs being parsed, from the root to the current one
+    pub fn new(structs: &'a [NodeRef]) -> Self {
+        Context {
+            structs,
+            last: None,
+            index: None,
+            io: None,
+            definitions: None,
+        }
+    }
+
+    /// Sets the last element read by a repetition, referred to as `_`
+    pub fn with_last(mut self, last: &'a NodeRef) -> Self {
+        self.last = Some(last);
+        self
+    }
+
+    /// Sets the index of the element being read by a repetition, referred to as `_index`
+    pub fn with_index(mut self, index: Option<usize>) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Sets the stream of the current struct, referred to as `_io`
+    pub fn with_io(mut self, io: &'a KaitaiStream<'a>) -> Self {
+        self.io = Some(io);
+        self
+    }
+
+    /// Sets the types, enums and instances the expressions can refer to
+    pub fn with_definitions(mut self, definitions: Box<dyn Definitions + 'a>) -> Self {
+        self.definitions = Some(definitions);
+        self
+    }
+
+    /// Evaluates an expression tree in this context
+    pub fn evaluate(&self, expr: &Expr) -> Result<Value, ExpressionError> {
+        match expr {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Name(name) => self.evaluate_name(name),
+            Expr::EnumMember { enum_name, member } => {
+                self.definitions()?.enum_value(enum_name, member)
+            }
+            Expr::Attribute { object, name } => match (object.as_ref(), name.as_str()) {
+                (Expr::Name(object_name), _) if object_name == "_io" => self.io_attribute(name),
+                (_, "_sizeof") => self.sizeof_node(object),
+                (_, "size") => match self.evaluate_indexable(object)? {
+                    Indexable::List(list) => {
+                        Ok(Value::Signed(list.borrow().get_children().len() as i64))
+                    }
+                    Indexable::Value(object) => self.evaluate_attribute(&object, name),
+                },
+                _ => self.evaluate_attribute(&self.evaluate(object)?, name),
+            },
+            Expr::MethodCall {
+                object,
+                name,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.evaluate(object)?.call_method(name, &arguments)
+            }
+            Expr::Index { object, index } => {
+                let object = self.evaluate_indexable(object)?;
+                let index = self.evaluate(index)?;
+                match object {
+                    Indexable::List(list) => {
+                        let list = list.borrow();
+                        let position = index_position(&index, list.get_children().len())?;
+                        Value::from_node(&list.get_children()[position])
+                    }
+                    Indexable::Value(object) => self.evaluate_index(&object, &index),
+                }
+            }
+            Expr::SizeOf(type_name) => {
+                Ok(Value::Signed(self.definitions()?.sizeof(type_name)? as i64))
+            }
+            Expr::Cast { object, type_name } => self.evaluate(object)?.cast(type_name),
+            Expr::Unary { operator, operand } => {
+                self.evaluate_unary(*operator, &self.evaluate(operand)?)
+            }
+            // The logical operators only evaluate their right operand if needed
+            Expr::Binary {
+                operator: BinaryOperator::And,
+                left,
+                right,
+            } => Ok(Value::Bool(
+                self.evaluate(left)?.to_bool()? && self.evaluate(right)?.to_bool()?,
+            )),
+            Expr::Binary {
+                operator: BinaryOperator::Or,
+                left,
+                right,
+            } => Ok(Value::Bool(
+                self.evaluate(left)?.to_bool()? || self.evaluate(right)?.to_bool()?,
+            )),
+            Expr::Binary {
+                operator,
+                left,
+                right,
+            } => binary_operation(*operator, &self.evaluate(left)?, &self.evaluate(right)?),
+            Expr::Ternary {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                if self.evaluate(condition)?.to_bool()? {
+                    self.evaluate(if_true)
+                } else {
+                    self.evaluate(if_false)
+                }
             }
         }
     }
 
-    // Try parsing an integer
-    if let Ok(pairs) = ExprParser::parse(Rule::integer, expr) {
-        for pair in pairs {
-            let integer_str = pair.as_str();
-            if let Some(integer_value) = parse_integer(integer_str) {
-                return integer_value;
+    // Gets the types, enums and instances of the format description
+    fn definitions(&self) -> Result<&dyn Definitions, ExpressionError> {
+        self.definitions.as_deref().ok_or_else(|| {
+            ExpressionError::Unsupported("types and enums outside of a format".to_string())
+        })
+    }
+
+    // Gets the top-level struct
+    fn root(&self) -> Result<&'a NodeRef, ExpressionError> {
+        self.structs
+            .first()
+            .ok_or_else(|| ExpressionError::UnknownName("_root".to_string()))
+    }
+
+    // Gets the struct containing the given struct
+    // Structs being parsed are found in the chain of structs, other ones are looked up from the root
+    fn parent_of(&self, node: &NodeRef) -> Result<NodeRef, ExpressionError> {
+        let no_parent = || ExpressionError::UnknownName("_parent".to_string());
+
+        if let Some(position) = self
+            .structs
+            .iter()
+            .position(|structure| Rc::ptr_eq(structure, node))
+        {
+            return position
+                .checked_sub(1)
+                .map(|parent_position| self.structs[parent_position].clone())
+                .ok_or_else(no_parent);
+        }
+
+        // The elements of a repeated attribute belong to the struct containing the attribute
+        let contains = |parent: &NodeRef| {
+            parent.borrow().get_children().iter().any(|child| {
+                Rc::ptr_eq(child, node)
+                    || (child.borrow().get_node_type() == Some(&NodeType::List)
+                        && child
+                            .borrow()
+                            .get_children()
+                            .iter()
+                            .any(|element| Rc::ptr_eq(element, node)))
+            })
+        };
+
+        let mut stack = vec![self.root()?.clone()];
+        while let Some(structure) = stack.pop() {
+            if contains(&structure) {
+                return Ok(structure);
             }
+            stack.extend(structure.borrow().get_children().iter().cloned());
+        }
+
+        Err(no_parent())
+    }
+
+    // Resolves an attribute or a built-in of a struct
+    fn struct_attribute(&self, structure: &NodeRef, name: &str) -> Result<Value, ExpressionError> {
+        match name {
+            "_root" => Ok(Value::Struct(self.root()?.clone())),
+            "_parent" => Ok(Value::Struct(self.parent_of(structure)?)),
+            "_io" => Err(ExpressionError::Unsupported(
+                "'_io' of another struct than the current one".to_string(),
+            )),
+            _ => Value::from_node(&self.child_node(structure, name)?),
+        }
+    }
+
+    // Gets an attribute node of a struct
+    // Instances are computed the first time they are used, they are attributes of the struct afterwards
+    fn child_node(&self, structure: &NodeRef, name: &str) -> Result<NodeRef, ExpressionError> {
+        let child = structure.borrow().get_child(name);
+        match (child, &self.definitions) {
+            (Some(child), _) => Ok(child),
+            (None, Some(definitions)) => definitions
+                .instance(structure, name)?
+                .ok_or_else(|| ExpressionError::UnknownName(name.to_string())),
+            (None, None) => Err(ExpressionError::UnknownName(name.to_string())),
         }
     }
 
-    // Default return value in case of errors or unsupported expressions
-    0
+    // Resolves the node an expression refers to, an attribute of a struct or an element of a repetition
+    fn evaluate_node(&self, expr: &Expr) -> Result<NodeRef, ExpressionError> {
+        match expr {
+            Expr::Name(name) if name == "_" => self
+                .last
+                .cloned()
+                .ok_or_else(|| ExpressionError::UnknownName(name.to_string())),
+            Expr::Name(name) if !name.starts_with('_') => {
+                let current = self
+                    .structs
+                    .last()
+                    .ok_or_else(|| ExpressionError::UnknownName(name.to_string()))?;
+                self.child_node(current, name)
+            }
+            Expr::Attribute { object, name } if !name.starts_with('_') => {
+                match self.evaluate(object)? {
+                    Value::Struct(structure) => self.child_node(&structure, name),
+                    object => Err(ExpressionError::TypeMismatch(format!(
+                        "cannot get attribute '{}' of {} {}",
+                        name,
+                        object.type_name(),
+                        object
+                    ))),
+                }
+            }
+            Expr::Index { object, index } => {
+                let list = self.evaluate_node(object)?;
+                let index = self.evaluate(index)?.to_usize()?;
+                let list = list.borrow();
+                list.get_children()
+                    .get(index)
+                    .cloned()
+                    .ok_or(ExpressionError::IndexOutOfBounds {
+                        index: index as i128,
+                        length: list.get_children().len(),
+                    })
+            }
+            _ => match self.evaluate(expr)? {
+                Value::Struct(structure) => Ok(structure),
+                value => Err(ExpressionError::TypeMismatch(format!(
+                    "expected an attribute, got {} {}",
+                    value.type_name(),
+                    value
+                ))),
+            },
+        }
+    }
+
+    // Evaluates the operand of an index or of `size`, keeping an attribute holding a list as its node
+    fn evaluate_indexable(&self, expr: &Expr) -> Result<Indexable, ExpressionError> {
+        let node = match expr {
+            Expr::Name(name) if !name.starts_with('_') => {
+                let current = self
+                    .structs
+                    .last()
+                    .ok_or_else(|| ExpressionError::UnknownName(name.to_string()))?;
+                self.child_node(current, name)?
+            }
+            Expr::Attribute { object, name }
+                if !name.starts_with('_')
+                    && !matches!(object.as_ref(), Expr::Name(object_name) if object_name == "_io") =>
+            {
+                match self.evaluate(object)? {
+                    Value::Struct(structure) => self.child_node(&structure, name)?,
+                    object => return self.evaluate_attribute(&object, name).map(Indexable::Value),
+                }
+            }
+            _ => return self.evaluate(expr).map(Indexable::Value),
+        };
+
+        if node.borrow().get_node_type() == Some(&NodeType::List) {
+            Ok(Indexable::List(node))
+        } else {
+            Value::from_node(&node).map(Indexable::Value)
+        }
+    }
+
+    // Gets the size in bytes of the attribute an expression refers to, for `attribute._sizeof`
+    fn sizeof_node(&self, expr: &Expr) -> Result<Value, ExpressionError> {
+        let node = self.evaluate_node(expr)?;
+        let node = node.borrow();
+        match node.get_span() {
+            Some((start, end)) => Ok(Value::Signed((end - start) as i64)),
+            None => Err(ExpressionError::Absent(
+                node.get_id().clone().unwrap_or_default(),
+            )),
+        }
+    }
+
+    // Gets a property of the stream of the current struct
+    fn io_attribute(&self, name: &str) -> Result<Value, ExpressionError> {
+        let io = self
+            .io
+            .ok_or_else(|| ExpressionError::UnknownName("_io".to_string()))?;
+
+        match name {
+            "pos" => Ok(Value::Signed(io.pos() as i64)),
+            "size" => Ok(Value::Signed(io.size() as i64)),
+            "eof" => Ok(Value::Bool(io.is_eof())),
+            _ => Err(ExpressionError::Unsupported(format!(
+                "stream property '_io.{}'",
+                name
+            ))),
+        }
+    }
+
+    // Resolves a name, from the current struct
+    fn evaluate_name(&self, name: &str) -> Result<Value, ExpressionError> {
+        match name {
+            "_" => {
+                return match self.last {
+                    Some(last) => Value::from_node(last),
+                    None => Err(ExpressionError::UnknownName(name.to_string())),
+                }
+            }
+            "_index" => {
+                return match self.index {
+                    Some(index) => Ok(Value::Signed(index as i64)),
+                    None => Err(ExpressionError::UnknownName(name.to_string())),
+                }
+            }
+            "_io" => {
+                return Err(ExpressionError::Unsupported(
+                    "'_io' other than '_io.pos', '_io.size' or '_io.eof'".to_string(),
+                ))
+            }
+            _ => {}
+        }
+
+        let current = self
+            .structs
+            .last()
+            .ok_or_else(|| ExpressionError::UnknownName(name.to_string()))?;
+        self.struct_attribute(current, name)
+    }
+
+    // Gets an attribute of a value
+    fn evaluate_attribute(&self, object: &Value, name: &str) -> Result<Value, ExpressionError> {
+        match object {
+            Value::Struct(structure) => self.struct_attribute(structure, name),
+            // Any other value has methods only
+            _ => object.call_method(name, &[]),
+        }
+    }
+
+    // Gets an element of an array or of a byte array
+    fn evaluate_index(&self, object: &Value, index: &Value) -> Result<Value, ExpressionError> {
+        match object {
+            Value::Array(values) => Ok(values[index_position(index, values.len())?].clone()),
+            Value::Bytes(bytes) => Ok(Value::Unsigned(
+                bytes[index_position(index, bytes.len())?] as u64,
+            )),
+            _ => Err(ExpressionError::TypeMismatch(format!(
+                "cannot index {} {}",
+                object.type_name(),
+                object
+            ))),
+        }
+    }
+
+    // Applies a unary operator to a value
+    fn evaluate_unary(
+        &self,
+        operator: UnaryOperator,
+        operand: &Value,
+    ) -> Result<Value, ExpressionError> {
+        match (operator, operand, operand.as_integer()) {
+            (UnaryOperator::Not, Value::Bool(value), _) => Ok(Value::Bool(!value)),
+            (UnaryOperator::Negate, Value::Float(value), _) => Ok(Value::Float(-value)),
+            (UnaryOperator::Negate, _, Some(value)) => Value::from_integer(-value),
+            (UnaryOperator::BitwiseNot, _, Some(value)) => Value::from_integer(!value),
+            _ => Err(ExpressionError::TypeMismatch(format!(
+                "cannot apply {:?} to {} {}",
+                operator,
+                operand.type_name(),
+                operand
+            ))),
+        }
+    }
+}
+
+/// Evaluates a kaitai language expression against an Abstract Syntax Tree (AST) and returns its typed value
+/// Names are resolved from the root of the AST
+pub fn evaluate(ast: &AST, expr: &str) -> Result<Value, ExpressionError> {
+    evaluate_in(&Context::new(std::slice::from_ref(ast.get_root())), expr)
+}
+
+/// Evaluates a kaitai language expression in the given context and returns its typed value
+pub fn evaluate_in(context: &Context, expr: &str) -> Result<Value, ExpressionError> {
+    context.evaluate(&parse_expression(expr)?)
 }
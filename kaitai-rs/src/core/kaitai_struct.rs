@@ -1,169 +1,1079 @@
+use crate::core::ast::EnumMember;
 use crate::core::ast::Node;
+use crate::core::ast::NodeRef;
 use crate::core::ast::NodeType;
 use crate::core::ast::AST;
-use crate::core::expression::evaluate;
+use crate::core::encoding::{decode, encode_terminator};
+use crate::core::error::{ContentsMismatchError, ExpressionError, InstanceError, KaitaiError};
+use crate::core::expression::{Context, Definitions, Expr};
+use crate::core::process::{self, apply_process};
+use crate::core::stream::{find_terminator, KaitaiStream};
+use crate::core::value::Value;
 use crate::ks_language::format_description::FormatDescription;
 use crate::ks_language::language::attribute::Attribute;
+use crate::ks_language::language::attribute::Process;
+use crate::ks_language::language::attribute::ProcessType;
 use crate::ks_language::language::attribute::Repeat;
-use crate::ks_language::language::kaitai_type::PureType;
-use crate::ks_language::language::kaitai_type::{parse_strz, parse_unsigned_integer};
+use crate::ks_language::language::enums::{Enum, Enums};
+use crate::ks_language::language::instances::Instances;
+use crate::ks_language::language::kaitai_type::{PureType, SwitchType, Type};
+use crate::ks_language::language::meta::EndianEnum;
+use crate::ks_language::language::meta::Meta;
+use crate::ks_language::language::seq::Seq;
+use crate::ks_language::language::types::{TypeSpec, Types};
+use crate::ks_language::parser::kaitai_type::parse_kaitai_type;
+use crate::ks_language::parser::parser::KSLanguageParser;
 
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read};
 use std::path::Path;
+use std::rc::Rc;
+use std::slice;
+
+/// A view over the sections of a type definition, either the top-level format or a `types` entry
+#[derive(Clone, Copy)]
+struct TypeScope<'a> {
+    // The name of the type, empty for the top-level format
+    name: &'a str,
+    meta: &'a Meta,
+    seq: &'a Seq,
+    types: &'a Types,
+    enums: &'a Enums,
+    instances: &'a Instances,
+}
+
+impl<'a> TypeScope<'a> {
+    // Creates the scope of the top-level format
+    fn from_format(format: &'a KSLanguageParser) -> Self {
+        TypeScope {
+            name: "",
+            meta: &format.meta,
+            seq: &format.seq,
+            types: &format.types,
+            enums: &format.enums,
+            instances: &format.instances,
+        }
+    }
+
+    // Creates the scope of a user-defined type
+    fn from_typespec(name: &'a str, typespec: &'a TypeSpec) -> Self {
+        TypeScope {
+            name,
+            meta: &typespec.meta,
+            seq: &typespec.seq,
+            types: &typespec.type_types,
+            enums: &typespec.type_enums,
+            instances: &typespec.instances,
+        }
+    }
+}
+
+/// A struct being parsed: the scopes of its type and its stream, from which its instances are computed
+#[derive(Clone)]
+struct StructFrame<'a> {
+    scopes: Vec<TypeScope<'a>>,
+    stream: KaitaiStream<'a>,
+}
+
+// Gets the scopes of the type of the current struct, the last of the given frames
+fn current_scopes<'b, 'a>(frames: &'b [StructFrame<'a>]) -> &'b [TypeScope<'a>] {
+    frames
+        .last()
+        .map(|frame| frame.scopes.as_slice())
+        .unwrap_or_default()
+}
+
+/// The types, enums and instances visible from a type, as referred to by the expressions of its attributes
+struct ScopeDefinitions<'a> {
+    kaitai_struct: &'a KaitaiStruct,
+    frames: &'a [StructFrame<'a>],
+    structs: &'a [NodeRef],
+    // The stream of the current struct at its current position, the frames only hold the streams of the structs at their start
+    stream: &'a KaitaiStream<'a>,
+}
+
+impl Definitions for ScopeDefinitions<'_> {
+    fn sizeof(&self, type_name: &str) -> Result<usize, ExpressionError> {
+        self.kaitai_struct
+            .sizeof_type(current_scopes(self.frames), type_name)
+    }
+
+    fn enum_value(&self, enum_name: &str, member: &str) -> Result<Value, ExpressionError> {
+        let (enum_instance, enum_path) = self
+            .kaitai_struct
+            .resolve_enum(current_scopes(self.frames), enum_name)
+            .map_err(|_| ExpressionError::UnknownName(enum_name.to_string()))?;
+        let value = enum_instance
+            .get_value(member)
+            .ok_or_else(|| ExpressionError::UnknownName(format!("{}::{}", enum_name, member)))?;
+
+        Ok(Value::Enum {
+            enum_name: enum_path,
+            value,
+        })
+    }
+
+    fn instance(
+        &self,
+        structure: &NodeRef,
+        name: &str,
+    ) -> Result<Option<NodeRef>, ExpressionError> {
+        // Only the structs being parsed have instances left to compute
+        let position = match self
+            .structs
+            .iter()
+            .rposition(|struct_node| Rc::ptr_eq(struct_node, structure))
+        {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        let attribute = match self.frames[position]
+            .scopes
+            .last()
+            .and_then(|scope| scope.instances.get_instance(name))
+        {
+            Some(attribute) => attribute,
+            None => return Ok(None),
+        };
+
+        // The instances of the current struct are computed from its current position
+        let stream = if position + 1 == self.structs.len() {
+            self.stream
+        } else {
+            &self.frames[position].stream
+        };
+
+        self.kaitai_struct
+            .parse_instance(
+                &self.frames[..=position],
+                &self.structs[..=position],
+                stream,
+                attribute,
+            )
+            .map(Some)
+            .map_err(|error| ExpressionError::Instance {
+                name: name.to_string(),
+                error: InstanceError {
+                    error: Box::new(error),
+                    depth: position,
+                },
+            })
+    }
+}
+
+// Gets the error of an instance of the struct at the given position in the structs being parsed, used by an expression
+// The error is located from the struct of the instance, the path of the attribute using the instance is dropped
+fn instance_error(error: KaitaiError, depth: usize) -> KaitaiError {
+    match error {
+        KaitaiError::Expression {
+            error: ExpressionError::Instance { error, .. },
+            ..
+        } if error.depth == depth => instance_error(*error.error, depth),
+        error => error,
+    }
+}
+
+// Gets the path of an enum from the types defining it (`body::kind`), enum values are told apart by this path
+fn enum_path(scopes: &[TypeScope], enum_name: &str) -> String {
+    scopes
+        .iter()
+        .map(|scope| scope.name)
+        .filter(|name| !name.is_empty())
+        .chain([enum_name])
+        .collect::<Vec<_>>()
+        .join("::")
+}
 
 /// Struct representing a Kaitai struct
 #[allow(dead_code)]
 pub struct KaitaiStruct {
-    data: Vec<u8>,
     pub ast: AST,
     format_description: FormatDescription,
+    // Custom process routines, by the name used in the `process` keys
+    processes: HashMap<String, Box<dyn process::Process>>,
+    // Whether invalid sequences in strings are replaced by U+FFFD instead of failing the parsing
+    lossy_strings: bool,
 }
 
 impl KaitaiStruct {
-    // Create a new instance of `KaitaiStruct` with an empty Vec<u8> for data, a new AST for ast, and the provided FormatDescription
+    // Create a new instance of `KaitaiStruct` with a new AST for ast, and the provided FormatDescription
     pub fn new(format_description: FormatDescription) -> Self {
-        let data = Vec::<u8>::new();
         let ast = AST::new();
 
         KaitaiStruct {
-            data,
             ast,
             format_description,
+            processes: HashMap::new(),
+            lossy_strings: false,
         }
     }
 
-    // Get the data from the `KaitaiStruct` instance
-    pub fn get_data(&self) -> &[u8] {
-        &self.data
+    /// Sets whether invalid sequences in strings are replaced by U+FFFD instead of failing the parsing
+    pub fn set_lossy_strings(&mut self, lossy_strings: bool) {
+        self.lossy_strings = lossy_strings;
+    }
+
+    /// Registers a custom process routine under the given name, e.g. `my_company.decrypt`
+    ///
+    /// Attributes with `process: my_company.decrypt(key)` are decoded by the routine,
+    /// a routine registered under the name of a built-in one replaces it
+    pub fn register_process<P: process::Process + 'static>(&mut self, name: &str, process: P) {
+        self.processes.insert(name.to_string(), Box::new(process));
+    }
+
+    // Resolves the endianness of a numeric type
+    // The type suffix takes precedence over the `meta/endian` default of the innermost type defining one,
+    // little-endian is used if none is set
+    fn resolve_endian(&self, scopes: &[TypeScope], endian: Option<EndianEnum>) -> EndianEnum {
+        endian
+            .or_else(|| {
+                scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.meta.get_endian())
+            })
+            .unwrap_or(EndianEnum::Le)
+    }
+
+    // Resolves the encoding of a string attribute
+    // The `encoding` key of the attribute takes precedence over the `meta/encoding` default of the innermost type
+    // defining one, UTF-8 is used if none is set
+    fn resolve_encoding<'a>(&self, scopes: &[TypeScope<'a>], attribute: &'a Attribute) -> &'a str {
+        attribute
+            .encoding
+            .as_deref()
+            .or_else(|| {
+                scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.meta.get_encoding())
+            })
+            .unwrap_or("UTF-8")
     }
 
-    // Parses an unsigned integer attribute
-    fn parse_unsigned_integer_attribute(
+    // Resolves the bit endianness of a bit-sized type
+    // The type suffix takes precedence over the `meta/bit-endian` default of the innermost type defining one,
+    // big-endian is used if none is set
+    fn resolve_bit_endian(
         &self,
-        attribute: &Attribute,
-        attribute_node: &mut Node,
-        data_offset: &mut usize,
-    ) {
-        let size = match &attribute.seq_type {
-            Some(seq_type) => match &seq_type.pure_type {
-                PureType::UnsignedInteger(size) => *size,
-                _ => return,
-            },
-            None => return,
+        scopes: &[TypeScope],
+        bit_endian: Option<EndianEnum>,
+    ) -> EndianEnum {
+        bit_endian
+            .or_else(|| {
+                scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.meta.get_bit_endian())
+            })
+            .unwrap_or(EndianEnum::Be)
+    }
+
+    // Resolves a user-defined type name ("header", "file::entry") from the given scopes
+    // The first part of the name is looked up from the innermost type outwards, the other parts in the nested types
+    // Returns the scopes of the resolved type, from the top-level format to the type itself
+    fn resolve_user_type<'a>(
+        &self,
+        scopes: &[TypeScope<'a>],
+        name: &str,
+    ) -> Result<Vec<TypeScope<'a>>, KaitaiError> {
+        let unknown_type = || KaitaiError::schema(format!("Unknown type '{}'", name));
+
+        let mut parts = name.split("::");
+        let first_part = parts.next().ok_or_else(unknown_type)?;
+
+        let (index, name, typespec) = scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, scope)| {
+                scope
+                    .types
+                    .get_typespec_entry(first_part)
+                    .map(|(name, typespec)| (index, name, typespec))
+            })
+            .ok_or_else(unknown_type)?;
+
+        let mut type_scopes = scopes[..=index].to_vec();
+        type_scopes.push(TypeScope::from_typespec(name, typespec));
+
+        for part in parts {
+            let (name, typespec) = type_scopes
+                .last()
+                .and_then(|scope| scope.types.get_typespec_entry(part))
+                .ok_or_else(unknown_type)?;
+            type_scopes.push(TypeScope::from_typespec(name, typespec));
+        }
+
+        Ok(type_scopes)
+    }
+
+    // Creates the context of the expressions of the current struct, the last of the given structs
+    fn context<'a>(
+        &'a self,
+        frames: &'a [StructFrame<'a>],
+        structs: &'a [NodeRef],
+        stream: &'a KaitaiStream<'a>,
+    ) -> Context<'a> {
+        Context::new(structs)
+            .with_io(stream)
+            .with_definitions(Box::new(ScopeDefinitions {
+                kaitai_struct: self,
+                frames,
+                structs,
+                stream,
+            }))
+    }
+
+    // Resolves an enum name ("animal", "file::animal") from the given scopes
+    // A qualified name is looked up in the type qualifying it, an unqualified one from the innermost type outwards
+    fn resolve_enum<'a>(
+        &self,
+        scopes: &[TypeScope<'a>],
+        name: &str,
+    ) -> Result<(&'a Enum, String), KaitaiError> {
+        let (enum_scopes, enum_name) = match name.rsplit_once("::") {
+            Some((type_name, enum_name)) => {
+                (self.resolve_user_type(scopes, type_name).ok(), enum_name)
+            }
+            None => (
+                scopes
+                    .iter()
+                    .rposition(|scope| scope.enums.get_enum(name).is_some())
+                    .map(|index| scopes[..=index].to_vec()),
+                name,
+            ),
         };
 
-        let value = parse_unsigned_integer(&self.data[*data_offset..], size as usize);
-        attribute_node.set_data(value);
-        attribute_node.set_node_type(NodeType::Integer);
-        *data_offset += size as usize;
+        enum_scopes
+            .and_then(|enum_scopes| {
+                let enum_instance = enum_scopes.last()?.enums.get_enum(enum_name)?;
+                Some((enum_instance, enum_path(&enum_scopes, enum_name)))
+            })
+            .ok_or_else(|| KaitaiError::schema(format!("Unknown enum '{}'", name)))
     }
 
-    // Parses a null-terminated string attribute
-    fn parse_stringz_attribute(
+    // Marks an integer attribute node as a value of an enum
+    // Values that are not members of the enum are kept, without a member name
+    fn parse_enum_attribute(
         &self,
+        scopes: &[TypeScope],
         attribute: &Attribute,
-        attribute_node: &mut Node,
-        data_offset: &mut usize,
-    ) {
-        let size = attribute
-            .size
-            .as_ref()
-            .and_then(|s| s.parse::<usize>().ok());
-        let terminator = attribute.terminator.unwrap_or(0);
-        let repeat_count = if let Some(repeat) = &attribute.repeat {
-            if let Repeat::Expr = repeat {
-                let count = evaluate(&self.ast, &attribute.repeat_expr.as_ref().unwrap());
-                count as usize
-            } else {
-                1
+        enum_name: &str,
+        attribute_node: &NodeRef,
+    ) -> Result<(), KaitaiError> {
+        let (enum_instance, enum_path) = self.resolve_enum(scopes, enum_name)?;
+
+        let mut attribute_node = attribute_node.borrow_mut();
+        let value = match attribute_node.get_node_type() {
+            Some(NodeType::Integer) => attribute_node.get_unsigned_integer().map(i128::from),
+            Some(NodeType::SignedInteger) => attribute_node.get_signed_integer().map(i128::from),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            KaitaiError::schema(format!(
+                "Enum '{}' used by the attribute '{}', which is not an integer",
+                enum_name,
+                attribute.id.as_deref().unwrap_or_default()
+            ))
+        })?;
+
+        attribute_node.set_enum_member(EnumMember {
+            enum_name: enum_path,
+            member: enum_instance.get_member(value).map(str::to_string),
+        });
+        Ok(())
+    }
+
+    // Computes the size in bytes of a type, for `sizeof<type>`
+    // Only types made of attributes of a fixed size have a size
+    fn sizeof_type(&self, scopes: &[TypeScope], type_name: &str) -> Result<usize, ExpressionError> {
+        let seq_type = parse_kaitai_type(type_name)
+            .map_err(|_| ExpressionError::UnknownName(type_name.to_string()))?;
+        if let PureType::UserType(name) = &seq_type.pure_type {
+            self.resolve_user_type(scopes, name)
+                .map_err(|_| ExpressionError::UnknownName(type_name.to_string()))?;
+        }
+
+        self.type_size_bits(scopes, &seq_type)
+            .map(|bits| bits.div_ceil(8))
+            .ok_or_else(|| {
+                ExpressionError::Unsupported(format!(
+                    "'sizeof<{}>' of a type without a fixed size",
+                    type_name
+                ))
+            })
+    }
+
+    // Computes the size in bits of a type, if it is fixed
+    fn type_size_bits(&self, scopes: &[TypeScope], seq_type: &Type) -> Option<usize> {
+        match &seq_type.pure_type {
+            PureType::UnsignedInteger(size)
+            | PureType::SignedInteger(size)
+            | PureType::FloatingPoint(size) => Some(*size as usize * 8),
+            PureType::BitSizedInteger(size) => Some(*size as usize),
+            PureType::Boolean => Some(1),
+            PureType::UserType(name) => {
+                let type_scopes = self.resolve_user_type(scopes, name).ok()?;
+                self.seq_size_bits(&type_scopes)
             }
-        } else {
-            1
+            _ => None,
+        }
+    }
+
+    // Computes the size in bits of the `seq` of the innermost type of the given scopes, if it is fixed
+    // Sizes and repetition counts must be constant expressions
+    fn seq_size_bits(&self, scopes: &[TypeScope]) -> Option<usize> {
+        // Constant expressions do not refer to any struct
+        let constant = |expr: &Expr| {
+            Context::new(&[])
+                .evaluate(expr)
+                .and_then(|value| value.to_usize())
+                .ok()
         };
 
-        for _ in 0..repeat_count {
-            let string_data = parse_strz(&self.data[*data_offset..], size, terminator);
-            *data_offset += string_data.len();
+        let mut bits: usize = 0;
+        for attribute in &scopes.last()?.seq.attributes {
+            if attribute.optional_if.is_some() || attribute.size_eos {
+                return None;
+            }
+
+            let count = match &attribute.repeat {
+                None => 1,
+                Some(Repeat::Expr) => constant(attribute.repeat_expr.as_ref()?)?,
+                Some(_) => return None,
+            };
+
+            let is_bits = matches!(
+                attribute
+                    .seq_type
+                    .as_ref()
+                    .map(|seq_type| &seq_type.pure_type),
+                Some(PureType::BitSizedInteger(_)) | Some(PureType::Boolean)
+            );
+            if !is_bits {
+                bits = bits.div_ceil(8) * 8;
+            }
 
-            if repeat_count > 1 {
-                let stringz_node = Node::new(None);
-                stringz_node.borrow_mut().set_data(string_data.clone());
-                stringz_node.borrow_mut().set_node_type(NodeType::String);
-                attribute_node.add_child(stringz_node);
+            let size = if let Some(size) = &attribute.size {
+                constant(size)? * 8
+            } else if let Some(seq_type) = &attribute.seq_type {
+                self.type_size_bits(scopes, seq_type)?
             } else {
-                attribute_node.set_data(string_data);
-                attribute_node.set_node_type(NodeType::String);
+                attribute.contents.as_ref()?.len() * 8
+            };
+            bits += size * count;
+        }
+
+        Some(bits)
+    }
+
+    // Parses a numeric (unsigned integer, signed integer or floating-point) attribute
+    fn parse_number_attribute(
+        &self,
+        scopes: &[TypeScope],
+        seq_type: &Type,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let (size, node_type) = match &seq_type.pure_type {
+            PureType::UnsignedInteger(size) => (*size, NodeType::Integer),
+            PureType::SignedInteger(size) => (*size, NodeType::SignedInteger),
+            PureType::FloatingPoint(size) => (*size, NodeType::Float),
+            _ => return Ok(()),
+        };
+
+        // The raw bytes are stored as read, the node type and endianness tell how to interpret them
+        let value = stream.read_bytes(size as usize)?;
+        let mut attribute_node = attribute_node.borrow_mut();
+        attribute_node.set_data(value);
+        attribute_node.set_node_type(node_type);
+        attribute_node.set_endian(self.resolve_endian(scopes, seq_type.endian));
+        Ok(())
+    }
+
+    // Parses a bit-sized integer or a boolean (single bit) attribute
+    fn parse_bits_attribute(
+        &self,
+        scopes: &[TypeScope],
+        seq_type: &Type,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let (size, node_type) = match &seq_type.pure_type {
+            PureType::BitSizedInteger(size) => (*size, NodeType::Integer),
+            PureType::Boolean => (1, NodeType::Boolean),
+            _ => return Ok(()),
+        };
+
+        let value = match self.resolve_bit_endian(scopes, seq_type.endian) {
+            EndianEnum::Be => stream.read_bits_int_be(size)?,
+            EndianEnum::Le => stream.read_bits_int_le(size)?,
+        };
+
+        // Store the value on the smallest number of bytes able to hold it, in big-endian order
+        let byte_count = (size as usize).div_ceil(8);
+        let mut attribute_node = attribute_node.borrow_mut();
+        attribute_node.set_data(value.to_be_bytes()[8 - byte_count..].to_vec());
+        attribute_node.set_node_type(node_type);
+        attribute_node.set_endian(EndianEnum::Be);
+        Ok(())
+    }
+
+    // Reads the bytes of a byte array or string attribute from its size and its terminator
+    // A sized attribute has its `pad-right` bytes stripped, then ends at the first terminator within it,
+    // otherwise the bytes are read up to the terminator as requested by `include`, `consume` and `eos-error`
+    // Returns `None` if the attribute has neither a size nor a terminator
+    fn read_delimited_bytes(
+        &self,
+        attribute: &Attribute,
+        size: Option<usize>,
+        terminator: Option<&[u8]>,
+        stream: &mut KaitaiStream,
+    ) -> Result<Option<Vec<u8>>, KaitaiError> {
+        match (size, terminator) {
+            (Some(size), _) => {
+                let mut bytes = stream.read_bytes(size)?;
+                if let Some(pad_right) = attribute.pad_right {
+                    let end = bytes
+                        .iter()
+                        .rposition(|&byte| byte != pad_right)
+                        .map_or(0, |last| last + 1);
+                    bytes.truncate(end);
+                }
+                if let Some(terminator) = terminator {
+                    if let Some(terminator_pos) = find_terminator(&bytes, terminator) {
+                        let end = if attribute.include {
+                            terminator_pos + terminator.len()
+                        } else {
+                            terminator_pos
+                        };
+                        bytes.truncate(end);
+                    }
+                }
+                Ok(Some(bytes))
             }
+            (None, Some(terminator)) => Ok(Some(stream.read_bytes_term(
+                terminator,
+                attribute.include,
+                attribute.consume,
+                attribute.eos_error,
+            )?)),
+            (None, None) => Ok(None),
         }
     }
 
-    // Parses a contents attribute
-    fn parse_contents_attribute(&self, attribute: &Attribute, attribute_node: &mut Node) {
+    // Parses a string attribute, decoding its bytes with its encoding
+    // The string is read from its size, or up to its terminator, `strz` strings being terminated by default
+    fn parse_string_attribute(
+        &self,
+        scopes: &[TypeScope],
+        attribute: &Attribute,
+        is_zero_terminated: bool,
+        size: Option<usize>,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let encoding = self.resolve_encoding(scopes, attribute);
+
+        // The terminator is a code unit of the encoding, `strz` strings end with a null one
+        let terminator = match (attribute.terminator, is_zero_terminated) {
+            (Some(terminator), _) => Some(encode_terminator(terminator, encoding)),
+            (None, true) => Some(encode_terminator(0, encoding)),
+            (None, false) => None,
+        };
+
+        let string_data = self
+            .read_delimited_bytes(attribute, size, terminator.as_deref(), stream)?
+            .ok_or_else(|| {
+                KaitaiError::schema(format!(
+                    "Missing 'size', 'size-eos' or 'terminator' field for string attribute '{}'",
+                    attribute.id.as_deref().unwrap_or_default()
+                ))
+            })?;
+
+        let string = decode(&string_data, encoding, self.lossy_strings).map_err(|error| {
+            KaitaiError::validation(format!(
+                "Cannot decode string attribute '{}': {}",
+                attribute.id.as_deref().unwrap_or_default(),
+                error
+            ))
+        })?;
+
+        let mut attribute_node = attribute_node.borrow_mut();
+        attribute_node.set_data(string_data);
+        attribute_node.set_string(string);
+        attribute_node.set_node_type(NodeType::String);
+        Ok(())
+    }
+
+    // Parses a user-defined type attribute into a subtree of the attribute node
+    fn parse_user_type_attribute(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        type_name: &str,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let type_scopes = self.resolve_user_type(current_scopes(frames), type_name)?;
+
+        attribute_node.borrow_mut().set_node_type(NodeType::Struct);
+        // The attribute node becomes the current struct of the expressions of the type
+        let mut type_structs = structs.to_vec();
+        type_structs.push(attribute_node.clone());
+        let mut type_frames = frames.to_vec();
+        type_frames.push(StructFrame {
+            scopes: type_scopes,
+            stream: stream.clone(),
+        });
+        self.parse_seq(&type_frames, &type_structs, stream)
+    }
+
+    // Parses a contents attribute, checking that the bytes read match the expected ones
+    fn parse_contents_attribute(
+        &self,
+        attribute: &Attribute,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
         if let Some(content) = &attribute.contents {
-            attribute_node.set_data(content.clone());
+            let offset = stream.pos();
+            let raw_data = stream.read_bytes(content.len())?;
+
+            if &raw_data != content {
+                return Err(KaitaiError::ContentsMismatch {
+                    path: String::new(),
+                    error: ContentsMismatchError {
+                        offset,
+                        expected: content.clone(),
+                        actual: raw_data,
+                    },
+                });
+            }
+
+            let mut attribute_node = attribute_node.borrow_mut();
+            attribute_node.set_data(raw_data);
+            attribute_node.set_node_type(NodeType::Array);
+        }
+
+        Ok(())
+    }
+
+    // Parses a byte array attribute without a type, delimited by its size or its terminator
+    fn parse_bytes_attribute(
+        &self,
+        attribute: &Attribute,
+        size: Option<usize>,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let terminator = attribute.terminator.map(|terminator| [terminator]);
+        let terminator = terminator.as_ref().map(|terminator| terminator.as_slice());
+        if let Some(raw_data) = self.read_delimited_bytes(attribute, size, terminator, stream)? {
+            let mut attribute_node = attribute_node.borrow_mut();
+            attribute_node.set_data(raw_data);
             attribute_node.set_node_type(NodeType::Array);
         }
+        Ok(())
     }
 
-    // Parses a size attribute
-    fn parse_size_attribute(
+    // Resolves the size in bytes of an attribute, from its `size` expression or from the bytes left in the stream for `size-eos`
+    // Returns `None` if the attribute is not sized
+    fn resolve_size(
         &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        index: Option<usize>,
         attribute: &Attribute,
-        attribute_node: &mut Node,
-        data_offset: &mut usize,
-    ) {
-        if let Some(size) = &attribute.size {
-            if let Ok(size) = size.parse::<usize>() {
-                let raw_data = self.data[*data_offset..*data_offset + size].to_vec();
-                attribute_node.set_data(raw_data);
-                attribute_node.set_node_type(NodeType::Array);
-                *data_offset += size;
+        stream: &KaitaiStream,
+    ) -> Result<Option<usize>, KaitaiError> {
+        if attribute.size_eos {
+            return Ok(Some(stream.remaining()));
+        }
+
+        match &attribute.size {
+            Some(size_expr) => Ok(Some(
+                self.context(frames, structs, stream)
+                    .with_index(index)
+                    .evaluate(size_expr)?
+                    .to_usize()?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // Resolves the type selected by a switch type from the value of its `switch-on` expression
+    // Returns `None` if no case matches and there is no default case
+    fn resolve_switch_type<'a>(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        index: Option<usize>,
+        switch_type: &'a SwitchType,
+        stream: &KaitaiStream,
+    ) -> Result<Option<&'a Type>, KaitaiError> {
+        let context = self.context(frames, structs, stream).with_index(index);
+        let switch_value = context.evaluate(&switch_type.switch_on)?;
+
+        for (case_expr, case_type) in &switch_type.cases {
+            if context.evaluate(case_expr)?.equals(&switch_value)? {
+                return Ok(Some(case_type));
             }
         }
+
+        Ok(switch_type.default.as_deref())
     }
 
     // Parses a single attribute
+    // `index` is the index of the element being parsed if the attribute is repeated
     fn parse_attribute(
         &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        index: Option<usize>,
         attribute: &Attribute,
-        attribute_node: &mut Node,
-        data_offset: &mut usize,
-    ) {
-        if attribute.size_eos {
-            attribute_node.set_data(self.data.clone());
-            attribute_node.set_node_type(NodeType::Array);
-        } else if let Some(seq_type) = &attribute.seq_type {
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        // Resolve the actual type of the attribute, switch types are resolved from the data parsed so far
+        let seq_type = match &attribute.seq_type {
+            Some(Type {
+                pure_type: PureType::Switch(switch_type),
+                ..
+            }) => self.resolve_switch_type(frames, structs, index, switch_type, stream)?,
+            seq_type => seq_type.as_ref(),
+        };
+
+        // Bit-sized attributes continue from the left over bits, any other attribute starts on a byte boundary
+        let is_bits = matches!(
+            seq_type.map(|seq_type| &seq_type.pure_type),
+            Some(PureType::BitSizedInteger(_)) | Some(PureType::Boolean)
+        );
+        if !is_bits {
+            stream.align_to_byte();
+        }
+
+        let size = self.resolve_size(frames, structs, index, attribute, stream)?;
+        let start = stream.pos();
+
+        let end = match &attribute.process {
+            // A processed attribute is parsed from its decoded bytes, the stream only moves past its raw bytes
+            Some(process) => {
+                let data =
+                    self.read_processed(frames, structs, index, attribute, process, size, stream)?;
+                let end = stream.pos();
+                let mut processed_stream = KaitaiStream::from_vec(data);
+                let processed_size = processed_stream.size();
+                self.parse_attribute_data(
+                    frames,
+                    structs,
+                    attribute,
+                    seq_type,
+                    Some(processed_size),
+                    attribute_node,
+                    &mut processed_stream,
+                )?;
+                end
+            }
+            None => {
+                self.parse_attribute_data(
+                    frames,
+                    structs,
+                    attribute,
+                    seq_type,
+                    size,
+                    attribute_node,
+                    stream,
+                )?;
+                stream.pos().max(start + size.unwrap_or(0))
+            }
+        };
+
+        if let Some(enum_name) = &attribute.attribute_enum {
+            self.parse_enum_attribute(
+                current_scopes(frames),
+                attribute,
+                enum_name,
+                attribute_node,
+            )?;
+        }
+
+        // The span covers the bytes read for the attribute, `_sizeof` is computed from it
+        attribute_node.borrow_mut().set_span(start, end);
+        Ok(())
+    }
+
+    // Reads the raw bytes of a processed attribute and applies its `process` routine to them
+    #[allow(clippy::too_many_arguments)]
+    fn read_processed(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        index: Option<usize>,
+        attribute: &Attribute,
+        process: &Process,
+        size: Option<usize>,
+        stream: &mut KaitaiStream,
+    ) -> Result<Vec<u8>, KaitaiError> {
+        let size = size.ok_or_else(|| {
+            KaitaiError::schema(format!(
+                "Missing 'size' or 'size-eos' field for processed attribute '{}'",
+                attribute.id.as_deref().unwrap_or_default()
+            ))
+        })?;
+
+        // The arguments are evaluated before reading, like the size
+        let arguments = {
+            let context = self.context(frames, structs, stream).with_index(index);
+            process
+                .arguments
+                .iter()
+                .map(|argument| context.evaluate(argument))
+                .collect::<Result<Vec<Value>, _>>()?
+        };
+
+        let raw = stream.read_bytes(size)?;
+        match (
+            self.processes.get(process.process_type.name()),
+            &process.process_type,
+        ) {
+            (Some(routine), _) => routine.decode(&arguments, &raw),
+            (None, ProcessType::Custom(name)) => {
+                return Err(KaitaiError::unsupported(format!(
+                    "Unknown process routine '{}', it must be registered on the parser",
+                    name
+                )))
+            }
+            (None, process_type) => apply_process(process_type, &arguments, &raw),
+        }
+        .map_err(|error| {
+            KaitaiError::validation(format!(
+                "Cannot process attribute '{}': {}",
+                attribute.id.as_deref().unwrap_or_default(),
+                error
+            ))
+        })
+    }
+
+    // Parses the data of an attribute according to its resolved type
+    // Attributes without a type are read as contents or as raw bytes of the given size
+    #[allow(clippy::too_many_arguments)]
+    fn parse_attribute_data(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        attribute: &Attribute,
+        seq_type: Option<&Type>,
+        size: Option<usize>,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        if let Some(seq_type) = seq_type {
             match &seq_type.pure_type {
-                PureType::UnsignedInteger(_) => {
-                    self.parse_unsigned_integer_attribute(attribute, attribute_node, data_offset);
+                PureType::UnsignedInteger(_)
+                | PureType::SignedInteger(_)
+                | PureType::FloatingPoint(_) => {
+                    self.parse_number_attribute(
+                        current_scopes(frames),
+                        seq_type,
+                        attribute_node,
+                        stream,
+                    )?;
                 }
-                PureType::StringZ => {
-                    self.parse_stringz_attribute(attribute, attribute_node, data_offset);
+                PureType::BitSizedInteger(_) | PureType::Boolean => {
+                    self.parse_bits_attribute(
+                        current_scopes(frames),
+                        seq_type,
+                        attribute_node,
+                        stream,
+                    )?;
                 }
-                _ => {
-                    // TODO: Implement parsing logic for other types
-                    todo!();
+                PureType::String | PureType::StringZ => {
+                    let is_zero_terminated = matches!(seq_type.pure_type, PureType::StringZ);
+                    self.parse_string_attribute(
+                        current_scopes(frames),
+                        attribute,
+                        is_zero_terminated,
+                        size,
+                        attribute_node,
+                        stream,
+                    )?;
+                }
+                PureType::UserType(type_name) => match size {
+                    // A sized user type is parsed from a substream, so that it cannot read past its own bytes
+                    Some(size) => {
+                        let mut substream = stream.substream(size)?;
+                        self.parse_user_type_attribute(
+                            frames,
+                            structs,
+                            type_name,
+                            attribute_node,
+                            &mut substream,
+                        )?;
+                    }
+                    None => {
+                        self.parse_user_type_attribute(
+                            frames,
+                            structs,
+                            type_name,
+                            attribute_node,
+                            stream,
+                        )?;
+                    }
+                },
+                pure_type => {
+                    return Err(KaitaiError::unsupported(format!(
+                        "Unsupported type {:?} for attribute '{}'",
+                        pure_type,
+                        attribute.id.as_deref().unwrap_or_default()
+                    )))
                 }
             }
         } else if attribute.contents.is_some() {
-            self.parse_contents_attribute(attribute, attribute_node);
-        } else if attribute.size.is_some() {
-            self.parse_size_attribute(attribute, attribute_node, data_offset);
+            self.parse_contents_attribute(attribute, attribute_node, stream)?;
+        } else {
+            // Also covers switch types without a matching case, which are read as raw bytes
+            self.parse_bytes_attribute(attribute, size, attribute_node, stream)?;
         }
+
+        Ok(())
     }
 
-    /// Parses the data and converts it into an AST
-    /// For now, it's just a naive implementation that only parses top-level attributes
-    /// TODO: Step-by-step improvements to manage more and more features
-    fn parse_data(&mut self) {
-        let root_node = self.ast.get_root().clone();
+    // Parses an attribute, repeating it as requested by its `repeat` key
+    // Each repetition is parsed into an unnamed child node of the attribute node, in order
+    fn parse_repeated_attribute(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        attribute: &Attribute,
+        attribute_node: &NodeRef,
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let repeat = match &attribute.repeat {
+            Some(repeat) => repeat,
+            None => {
+                return self.parse_attribute(
+                    frames,
+                    structs,
+                    None,
+                    attribute,
+                    attribute_node,
+                    stream,
+                )
+            }
+        };
+
+        let missing_key = |key: &str| {
+            KaitaiError::schema(format!(
+                "Missing '{}' field for repeated attribute '{}'",
+                key,
+                attribute.id.as_deref().unwrap_or_default()
+            ))
+        };
+
+        // Parses the next element and adds it to the attribute node, errors are located by the index of the element
+        let parse_element =
+            |index: usize, stream: &mut KaitaiStream| -> Result<NodeRef, KaitaiError> {
+                let element_node = Node::new(None);
+                attribute_node.borrow_mut().add_child(element_node.clone());
+                let start = stream.pos();
+                self.parse_attribute(
+                    frames,
+                    structs,
+                    Some(index),
+                    attribute,
+                    &element_node,
+                    stream,
+                )
+                .map_err(|error| error.in_attribute(&format!("[{}]", index), Some(start)))?;
+                Ok(element_node)
+            };
+
+        attribute_node.borrow_mut().set_node_type(NodeType::List);
+        let start = stream.pos();
+
+        match repeat {
+            Repeat::Expr => {
+                let repeat_expr = attribute
+                    .repeat_expr
+                    .as_ref()
+                    .ok_or_else(|| missing_key("repeat-expr"))?;
+                let count = self
+                    .context(frames, structs, stream)
+                    .evaluate(repeat_expr)?
+                    .to_usize()?;
+                for index in 0..count {
+                    parse_element(index, stream)?;
+                }
+            }
+            Repeat::Eos => {
+                // An element reading nothing would be read again forever, e.g. one with a size of 0 read from the data
+                let mut index = 0;
+                while !stream.is_eof() {
+                    let element_start = (stream.pos(), stream.bits_left());
+                    parse_element(index, stream)?;
+                    if (stream.pos(), stream.bits_left()) == element_start {
+                        return Err(KaitaiError::validation(format!(
+                            "Element {} of the repeated attribute '{}' read no data, it would be repeated forever",
+                            index,
+                            attribute.id.as_deref().unwrap_or_default()
+                        )));
+                    }
+                    index += 1;
+                }
+            }
+            Repeat::Until => {
+                let repeat_until = attribute
+                    .repeat_until
+                    .as_ref()
+                    .ok_or_else(|| missing_key("repeat-until"))?;
+                // The condition is checked after each element, `_` being the element just read
+                for index in 0.. {
+                    let element_node = parse_element(index, stream)?;
+                    let context = self
+                        .context(frames, structs, stream)
+                        .with_index(Some(index))
+                        .with_last(&element_node);
+                    if context.evaluate(repeat_until)?.to_bool()? {
+                        break;
+                    }
+                }
+            }
+        }
+
+        attribute_node.borrow_mut().set_span(start, stream.pos());
+        Ok(())
+    }
+
+    // Parses the `seq` attributes of the type of the current struct as children of the current struct node
+    // The struct nodes go from the root to the current one, they are the context of the expressions
+    // The instances of the type that were not used by the attributes are computed afterwards
+    fn parse_seq(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        self.parse_seq_and_instances(frames, structs, stream)
+            .map_err(|error| instance_error(error, structs.len().saturating_sub(1)))
+    }
 
-        // Keep track of the current offset in the data
-        let mut data_offset = 0;
+    // Parses the attributes and the instances of the current struct, for `parse_seq`
+    fn parse_seq_and_instances(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        stream: &mut KaitaiStream,
+    ) -> Result<(), KaitaiError> {
+        let (scope, struct_node) = match (current_scopes(frames).last(), structs.last()) {
+            (Some(scope), Some(struct_node)) => (scope, struct_node),
+            _ => return Ok(()),
+        };
 
-        // Iterate through top-level attributes defined in the format description
-        for attribute in &self.format_description.format.seq.attributes {
+        for attribute in &scope.seq.attributes {
             // Init the attribute node
             // We use a default ID that is replaced afterwards
             let attribute_id = attribute
@@ -172,28 +1082,281 @@ impl KaitaiStruct {
                 .unwrap_or_else(|| "default_id".to_string());
             let attribute_node = Node::new(Some(attribute_id.clone()));
 
+            // The `if` condition is evaluated against the attributes parsed so far
+            let start = stream.pos();
+            let is_present = match &attribute.optional_if {
+                Some(condition) => self
+                    .context(frames, structs, stream)
+                    .evaluate(condition)
+                    .and_then(|value| value.to_bool())
+                    .map_err(|error| {
+                        KaitaiError::from(error).in_attribute(&attribute_id, Some(start))
+                    })?,
+                None => true,
+            };
+
+            // The node is added to the struct before being parsed, so that it is reachable while its children are parsed
+            struct_node.borrow_mut().add_child(attribute_node.clone());
+
+            if is_present {
+                self.parse_repeated_attribute(frames, structs, attribute, &attribute_node, stream)
+                    .map_err(|error| error.in_attribute(&attribute_id, Some(start)))?;
+            } else {
+                // Skipped attributes are kept in the AST, without data, so that they can be told apart from empty ones
+                attribute_node.borrow_mut().set_node_type(NodeType::Absent);
+            }
+        }
+
+        // The instances that were not used are computed for the AST, those pointing past the end of the stream are
+        // left out instead of failing the parsing, an instance being only read when it is used
+        // Any other error is an error of the format description, which is reported
+        for (_, attribute) in scope.instances.iter() {
+            let is_computed = attribute
+                .id
+                .as_deref()
+                .is_some_and(|id| struct_node.borrow().get_child(id).is_some());
+            if is_computed {
+                continue;
+            }
+
+            match self
+                .parse_instance(frames, structs, stream, attribute)
+                .map_err(|error| instance_error(error, structs.len() - 1))
             {
-                let mut attribute_node_borrowed = attribute_node.borrow_mut();
-                self.parse_attribute(attribute, &mut attribute_node_borrowed, &mut data_offset);
-            } // Borrow of attribute_node ends here
+                Ok(_) | Err(KaitaiError::EndOfStream { .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Computes an instance of the current struct and adds it to the struct node
+    // `stream` is the stream of the struct at its current position, the `_io` of the expressions of the instance
+    // Positional instances are read from a copy of the stream, so that its position is left unchanged
+    fn parse_instance(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        stream: &KaitaiStream,
+        attribute: &Attribute,
+    ) -> Result<NodeRef, KaitaiError> {
+        let struct_node = structs
+            .last()
+            .ok_or_else(|| KaitaiError::schema("Instance computed outside of a struct"))?;
+
+        // The node is added before being computed, so that an instance referring to itself is reported as absent
+        let instance_node = Node::new(attribute.id.clone());
+        struct_node.borrow_mut().add_child(instance_node.clone());
 
-            // Add the attribute node to the root node outside of the borrow scope
-            root_node.borrow_mut().add_child(attribute_node);
+        // Errors are located by the name of the instance, and by its position once it is known
+        // An instance that cannot be computed is left out of the struct
+        let mut pos = None;
+        let id = attribute.id.as_deref().unwrap_or_default();
+        if let Err(error) =
+            self.compute_instance(frames, structs, stream, attribute, &instance_node, &mut pos)
+        {
+            struct_node.borrow_mut().remove_child(id);
+            return Err(error.in_attribute(id, pos));
         }
+        Ok(instance_node)
     }
 
-    // Parses a file and loads its contents into the `KaitaiStruct` instance
-    pub fn parse_file<P: AsRef<Path>>(&mut self, path: P) -> std::io::Result<()> {
-        let mut file = File::open(path)?;
-        let mut data = Vec::new();
-        file.read_to_end(&mut data)?;
+    // Computes the value or reads the data of an instance into its node
+    // `pos` is set to the position the instance is read from, before reading it, value instances have none
+    fn compute_instance(
+        &self,
+        frames: &[StructFrame],
+        structs: &[NodeRef],
+        stream: &KaitaiStream,
+        attribute: &Attribute,
+        instance_node: &NodeRef,
+        pos: &mut Option<usize>,
+    ) -> Result<(), KaitaiError> {
+        let context = self.context(frames, structs, stream);
+        let is_present = match &attribute.optional_if {
+            Some(condition) => context.evaluate(condition)?.to_bool()?,
+            None => true,
+        };
+        if !is_present {
+            instance_node.borrow_mut().set_node_type(NodeType::Absent);
+            return Ok(());
+        }
 
-        self.data = data;
-        self.ast = AST::new();
+        if let Some(value) = &attribute.value {
+            let value = context.evaluate(value)?;
+            return self.store_value(current_scopes(frames), &value, instance_node);
+        }
+
+        let mut stream = match &attribute.io {
+            Some(io) => self.resolve_io(&context, frames, structs, stream, io)?,
+            None => stream.clone(),
+        };
+        if let Some(pos_expr) = &attribute.pos {
+            stream.seek(context.evaluate(pos_expr)?.to_usize()?)?;
+        }
+        *pos = Some(stream.pos());
+
+        self.parse_repeated_attribute(frames, structs, attribute, instance_node, &mut stream)
+    }
 
-        // Parse the file data
-        self.parse_data();
+    // Resolves the stream given by the `io` key of an instance, the `_io` of the current struct or of an enclosing one
+    // `stream` is the stream of the current struct at its current position
+    fn resolve_io<'a>(
+        &self,
+        context: &Context,
+        frames: &[StructFrame<'a>],
+        structs: &[NodeRef],
+        stream: &KaitaiStream<'a>,
+        io: &Expr,
+    ) -> Result<KaitaiStream<'a>, KaitaiError> {
+        let position = match io {
+            Expr::Name(name) if name == "_io" => Some(structs.len() - 1),
+            Expr::Attribute { object, name } if name == "_io" => match context.evaluate(object)? {
+                Value::Struct(structure) => structs
+                    .iter()
+                    .rposition(|struct_node| Rc::ptr_eq(struct_node, &structure)),
+                _ => None,
+            },
+            _ => None,
+        };
 
+        match position {
+            Some(position) if position + 1 == structs.len() => Some(stream.clone()),
+            Some(position) => frames.get(position).map(|frame| frame.stream.clone()),
+            None => None,
+        }
+        .ok_or_else(|| {
+                KaitaiError::schema(format!(
+                    "Invalid 'io' for instance: {:?}, expected the '_io' of the current struct or of an enclosing one",
+                    io
+                ))
+            })
+    }
+
+    // Stores the value computed for a value instance in its node
+    fn store_value(
+        &self,
+        scopes: &[TypeScope],
+        value: &Value,
+        node_ref: &NodeRef,
+    ) -> Result<(), KaitaiError> {
+        let mut node = node_ref.borrow_mut();
+        match value {
+            Value::Signed(value) => {
+                node.set_data(value.to_le_bytes().to_vec());
+                node.set_node_type(NodeType::SignedInteger);
+                node.set_endian(EndianEnum::Le);
+            }
+            Value::Unsigned(value) => {
+                node.set_data(value.to_le_bytes().to_vec());
+                node.set_node_type(NodeType::Integer);
+                node.set_endian(EndianEnum::Le);
+            }
+            Value::Float(value) => {
+                node.set_data(value.to_le_bytes().to_vec());
+                node.set_node_type(NodeType::Float);
+                node.set_endian(EndianEnum::Le);
+            }
+            Value::Bool(value) => {
+                node.set_data(vec![*value as u8]);
+                node.set_node_type(NodeType::Boolean);
+            }
+            Value::String(value) => {
+                node.set_data(value.as_bytes().to_vec());
+                node.set_string(value.clone());
+                node.set_node_type(NodeType::String);
+            }
+            Value::Bytes(value) => {
+                node.set_data(value.clone());
+                node.set_node_type(NodeType::Array);
+            }
+            Value::Enum { enum_name, value } => {
+                drop(node);
+                self.store_value(scopes, &Value::from_integer(*value)?, node_ref)?;
+                // Enum values hold the path of their enum, which is resolved from the top-level format
+                let member = self
+                    .resolve_enum(
+                        scopes.first().map(slice::from_ref).unwrap_or_default(),
+                        enum_name,
+                    )
+                    .ok()
+                    .and_then(|(enum_instance, _)| enum_instance.get_member(*value))
+                    .map(str::to_string);
+                node_ref.borrow_mut().set_enum_member(EnumMember {
+                    enum_name: enum_name.clone(),
+                    member,
+                });
+            }
+            Value::Array(values) => {
+                node.set_node_type(NodeType::List);
+                for value in values {
+                    let element_node = Node::new(None);
+                    self.store_value(scopes, value, &element_node)?;
+                    node.add_child(element_node);
+                }
+            }
+            // The struct is not moved, its attributes are shared with the instance
+            Value::Struct(structure) => {
+                node.set_node_type(NodeType::Struct);
+                for child in structure.borrow().get_children() {
+                    node.add_child(child.clone());
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Parses the data and converts it into an AST
+    fn parse_data(&self, ast: &AST, mut stream: KaitaiStream) -> Result<(), KaitaiError> {
+        let structs = [ast.get_root().clone()];
+
+        let frames = [StructFrame {
+            scopes: vec![TypeScope::from_format(&self.format_description.format)],
+            stream: stream.clone(),
+        }];
+        self.parse_seq(&frames, &structs, &mut stream)
+    }
+
+    /// Parses the data of a stream from its current position and returns the resulting AST
+    ///
+    /// The parser is left unchanged, so that it can parse several inputs, and streams over
+    /// readers are only read as far as the format description requires
+    pub fn parse(&self, stream: KaitaiStream) -> Result<AST, KaitaiError> {
+        let ast = AST::new();
+        self.parse_data(&ast, stream)?;
+        Ok(ast)
+    }
+
+    /// Parses in-memory data and returns the resulting AST
+    pub fn parse_bytes(&self, data: &[u8]) -> Result<AST, KaitaiError> {
+        self.parse(KaitaiStream::new(data))
+    }
+
+    /// Reads all the data of a reader, e.g. a network connection, parses it and returns the resulting AST
+    ///
+    /// Readers that can seek are better parsed from `KaitaiStream::from_reader`, which does not
+    /// load their data into memory
+    pub fn parse_reader<R: Read>(&self, mut reader: R) -> Result<AST, KaitaiError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        self.parse(KaitaiStream::from_vec(data))
+    }
+
+    /// Parses the data of a stream into the `ast` of the `KaitaiStruct` instance
+    pub fn parse_stream(&mut self, stream: KaitaiStream) -> Result<(), KaitaiError> {
+        self.ast = AST::new();
+        self.parse_data(&self.ast, stream)
+    }
+
+    /// Parses a file and loads its contents into the `ast` of the `KaitaiStruct` instance
+    ///
+    /// The file is read as it is parsed, and not loaded into memory. Errors in the data are
+    /// located by the path of the attribute being parsed, e.g. `header.entries[2].name`, and
+    /// by its offset in its stream
+    pub fn parse_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), KaitaiError> {
+        let file = File::open(path)?;
+        let stream = KaitaiStream::from_reader(BufReader::new(file))?;
+        self.parse_stream(stream)
+    }
 }
@@ -1,3 +1,8 @@
 pub mod ast;
+pub mod encoding;
+pub mod error;
 pub mod expression;
 pub mod kaitai_struct;
+pub mod process;
+pub mod stream;
+pub mod value;
@@ -0,0 +1,157 @@
+use crate::core::value::Value;
+use crate::ks_language::language::attribute::ProcessType;
+use flate2::read::ZlibDecoder;
+use std::io;
+use std::io::Read;
+
+/// A custom `process` routine, registered on a `KaitaiStruct` under the name used in format descriptions
+///
+/// ```
+/// use kaitai_rs::core::process::Process;
+/// use kaitai_rs::core::value::Value;
+/// use std::io;
+///
+/// // Subtracts a constant from every byte: `process: my_company.shift(3)`
+/// struct Shift;
+///
+/// impl Process for Shift {
+///     fn decode(&self, arguments: &[Value], data: &[u8]) -> io::Result<Vec<u8>> {
+///         let amount = arguments
+///             .first()
+///             .and_then(|amount| amount.as_integer())
+///             .unwrap_or(0) as u8;
+///         Ok(data.iter().map(|byte| byte.wrapping_sub(amount)).collect())
+///     }
+/// }
+/// ```
+pub trait Process {
+    /// Decodes the raw bytes of an attribute
+    ///
+    /// The arguments are the values of the expressions given to the routine, in order
+    fn decode(&self, arguments: &[Value], data: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// Applies a built-in `process` routine to the raw bytes of an attribute and returns the decoded bytes
+///
+/// The arguments are the values of the expressions given to the routine, e.g. the key of `xor(key)`
+pub fn apply_process(
+    process_type: &ProcessType,
+    arguments: &[Value],
+    data: &[u8],
+) -> io::Result<Vec<u8>> {
+    match (process_type, arguments) {
+        (ProcessType::Zlib, []) => process_zlib(data),
+        (ProcessType::Xor, [key]) => process_xor(key, data),
+        (ProcessType::Rol, [amount]) => process_rotate_left(amount, data),
+        (ProcessType::Rol, [amount, group_size]) if is_single_byte_group(group_size) => {
+            process_rotate_left(amount, data)
+        }
+        // Rotating right is rotating left by the complement of the amount
+        (ProcessType::Ror, [amount]) => process_rotate_left(&negate(amount)?, data),
+        (ProcessType::Ror, [amount, group_size]) if is_single_byte_group(group_size) => {
+            process_rotate_left(&negate(amount)?, data)
+        }
+        (ProcessType::Custom(name), _) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Unknown process routine '{}', custom routines must be registered on the parser",
+                name
+            ),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Invalid arguments for process '{}': expected {}, got {} argument(s)",
+                process_type.name(),
+                process_type.expected_arguments(),
+                arguments.len()
+            ),
+        )),
+    }
+}
+
+// Decompresses zlib data
+fn process_zlib(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(data)
+        .read_to_end(&mut decompressed)
+        .map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid zlib data: {}", error),
+            )
+        })?;
+    Ok(decompressed)
+}
+
+// XORs the data with a single byte key or with a multi-byte key repeated over the data
+fn process_xor(key: &Value, data: &[u8]) -> io::Result<Vec<u8>> {
+    let key = match key {
+        Value::Bytes(bytes) => bytes.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(to_byte)
+            .collect::<io::Result<Vec<u8>>>()?,
+        value => vec![to_byte(value)?],
+    };
+
+    if key.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid key for process 'xor': the key is empty",
+        ));
+    }
+
+    Ok(data
+        .iter()
+        .zip(key.iter().cycle())
+        .map(|(byte, key_byte)| byte ^ key_byte)
+        .collect())
+}
+
+// Rotates every byte of the data to the left by the given number of bits
+fn process_rotate_left(amount: &Value, data: &[u8]) -> io::Result<Vec<u8>> {
+    let amount = match amount.as_integer() {
+        Some(amount) => amount.rem_euclid(8) as u32,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid amount for process 'rol'/'ror': expected an integer, got {}",
+                    amount.type_name()
+                ),
+            ))
+        }
+    };
+
+    Ok(data.iter().map(|byte| byte.rotate_left(amount)).collect())
+}
+
+// Negates an integer amount, so that a right rotation can be done as a left one
+fn negate(amount: &Value) -> io::Result<Value> {
+    match amount.as_integer() {
+        Some(amount) => Ok(Value::from_integer(-amount)?),
+        None => Ok(amount.clone()),
+    }
+}
+
+// Checks whether the group size argument of `rol`/`ror` is 1, the only supported group size
+fn is_single_byte_group(group_size: &Value) -> bool {
+    group_size.as_integer() == Some(1)
+}
+
+// Converts an integer value to a byte of a key
+fn to_byte(value: &Value) -> io::Result<u8> {
+    value
+        .as_integer()
+        .and_then(|value| u8::try_from(value).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid key for process 'xor': expected a byte or a byte array, got {:?}",
+                    value
+                ),
+            )
+        })
+}
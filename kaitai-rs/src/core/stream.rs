@@ -0,0 +1,433 @@
+use crate::core::error::EndOfStreamError;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+/// The number of bytes first read from a reader while looking for a terminator
+const TERMINATOR_FIRST_CHUNK_SIZE: usize = 64;
+
+/// The largest number of bytes read at once from a reader while looking for a terminator
+const TERMINATOR_CHUNK_SIZE: usize = 64 * 1024;
+
+// A source of data that can be read at any position, shared by the copies of a stream
+trait ReadAt {
+    // Reads exactly enough bytes to fill the buffer, from the given position of the source
+    fn read_at(&self, pos: u64, buffer: &mut [u8]) -> io::Result<()>;
+
+    // Gives back the last bytes read, from the given position, which were not consumed
+    // The next read from this position gets them again without reading the source
+    fn unread(&self, pos: u64, bytes: &[u8]);
+}
+
+// A reader, its current position, unknown after a failed read, and the bytes given back before this position
+// The position is tracked so that sequential reads do not seek, which would discard the buffer of a `BufReader`
+struct SharedReader<R> {
+    reader: RefCell<(R, Option<u64>, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> ReadAt for SharedReader<R> {
+    fn read_at(&self, mut pos: u64, mut buffer: &mut [u8]) -> io::Result<()> {
+        let (reader, reader_pos, unread) = &mut *self.reader.borrow_mut();
+        if !unread.is_empty() && *reader_pos == Some(pos + unread.len() as u64) {
+            let size = buffer.len().min(unread.len());
+            buffer[..size].copy_from_slice(&unread[..size]);
+            unread.drain(..size);
+            buffer = &mut buffer[size..];
+            pos += size as u64;
+            if buffer.is_empty() {
+                return Ok(());
+            }
+        }
+        unread.clear();
+
+        if *reader_pos != Some(pos) {
+            *reader_pos = None;
+            reader.seek(SeekFrom::Start(pos))?;
+        }
+
+        *reader_pos = None;
+        reader.read_exact(buffer)?;
+        *reader_pos = Some(pos + buffer.len() as u64);
+        Ok(())
+    }
+
+    fn unread(&self, pos: u64, bytes: &[u8]) {
+        let (_, reader_pos, unread) = &mut *self.reader.borrow_mut();
+        if *reader_pos == Some(pos + bytes.len() as u64) {
+            *unread = bytes.to_vec();
+        }
+    }
+}
+
+/// The data read by a stream, borrowed from the caller, owned by the stream or read on demand from a reader
+///
+/// Owned data holds bytes computed during the parsing, such as the decoded bytes
+/// of a processed attribute. Owned data and readers are shared between the copies of the stream.
+#[derive(Clone)]
+enum StreamData<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Rc<[u8]>),
+    Reader(Rc<dyn ReadAt + 'a>),
+}
+
+impl fmt::Debug for StreamData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamData::Borrowed(data) => f.debug_tuple("Borrowed").field(data).finish(),
+            StreamData::Owned(data) => f.debug_tuple("Owned").field(data).finish(),
+            StreamData::Reader(_) => f.write_str("Reader"),
+        }
+    }
+}
+
+/// A bit-aware stream over the data being parsed
+///
+/// The data is an in-memory slice, e.g. the contents of a memory-mapped file, or any
+/// `Read + Seek` source, e.g. a file or a block device, whose bytes are only read when needed.
+///
+/// The stream keeps track of the current byte position and of the bits left
+/// over in the current byte after a bit-sized read. Byte-sized reads are only
+/// meaningful on a byte boundary, `align_to_byte` discards the left over bits.
+///
+/// ```
+/// use kaitai_rs::core::stream::KaitaiStream;
+/// use std::io::Cursor;
+///
+/// let mut stream = KaitaiStream::from_reader(Cursor::new(vec![0x01, 0x02, 0x03])).unwrap();
+/// assert_eq!(stream.read_bytes(2).unwrap(), vec![0x01, 0x02]);
+/// assert_eq!(stream.size(), 3);
+/// assert!(!stream.is_eof());
+/// ```
+#[derive(Debug, Clone)]
+pub struct KaitaiStream<'a> {
+    /// The data read by the stream
+    data: StreamData<'a>,
+
+    /// The offset in the data where the stream starts, substreams share the data of their parent
+    offset: usize,
+
+    /// The size of the stream in bytes
+    size: usize,
+
+    /// The current byte position in the stream
+    pos: usize,
+
+    /// The bits read from the data but not consumed yet
+    bits: u64,
+
+    /// The number of bits left in `bits`
+    bits_left: u8,
+}
+
+impl<'a> KaitaiStream<'a> {
+    /// Creates a new `KaitaiStream` positioned at the start of the given data
+    ///
+    /// Memory-mapped files can be parsed without being read into memory by passing the mapped bytes
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_data(StreamData::Borrowed(data), data.len())
+    }
+
+    /// Creates a new `KaitaiStream` owning the given data, positioned at its start
+    pub fn from_vec(data: Vec<u8>) -> Self {
+        let size = data.len();
+        Self::with_data(StreamData::Owned(data.into()), size)
+    }
+
+    /// Creates a new `KaitaiStream` reading from the given source, positioned at its start
+    ///
+    /// The size of the stream is the size of the source when the stream is created. Bytes are
+    /// read from the source when they are parsed, the copies of the stream share the source.
+    pub fn from_reader<R: Read + Seek + 'a>(mut reader: R) -> io::Result<Self> {
+        let end = reader.seek(SeekFrom::End(0))?;
+        let size = usize::try_from(end).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("The source is too large to be parsed: {} bytes", end),
+            )
+        })?;
+
+        let shared_reader = SharedReader {
+            reader: RefCell::new((reader, Some(end), Vec::new())),
+        };
+        Ok(Self::with_data(
+            StreamData::Reader(Rc::new(shared_reader)),
+            size,
+        ))
+    }
+
+    // Creates a new stream over the whole data, positioned at its start
+    fn with_data(data: StreamData<'a>, size: usize) -> Self {
+        KaitaiStream {
+            data,
+            offset: 0,
+            size,
+            pos: 0,
+            bits: 0,
+            bits_left: 0,
+        }
+    }
+
+    /// Gets the current byte position in the stream
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Gets the number of bits left over in the current byte after a bit-sized read
+    pub fn bits_left(&self) -> u8 {
+        self.bits_left
+    }
+
+    /// Gets the total size of the stream in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets the number of bytes left from the current position to the end of the stream
+    pub fn remaining(&self) -> usize {
+        self.size.saturating_sub(self.pos)
+    }
+
+    /// Checks whether the end of the stream has been reached
+    pub fn is_eof(&self) -> bool {
+        self.bits_left == 0 && self.pos >= self.size
+    }
+
+    /// Moves to the given byte position, discarding the bits left over in the current byte
+    ///
+    /// The position may be past the end of the stream, the next read then fails with an
+    /// `EndOfStreamError`
+    pub fn seek(&mut self, pos: usize) -> io::Result<()> {
+        self.align_to_byte();
+        self.pos = pos;
+        Ok(())
+    }
+
+    /// Discards the bits left over in the current byte
+    pub fn align_to_byte(&mut self) {
+        self.bits = 0;
+        self.bits_left = 0;
+    }
+
+    // Creates the error of a read of the given number of bytes going past the end of the stream
+    fn end_of_stream(&self, requested: Option<usize>) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            EndOfStreamError {
+                offset: self.pos,
+                requested,
+                remaining: self.remaining(),
+            },
+        )
+    }
+
+    // Gets the given number of bytes at the given position of the stream, without moving
+    // The bytes must be within the stream, slices are borrowed and readers are read from
+    fn read_at(&self, pos: usize, size: usize) -> io::Result<Cow<'_, [u8]>> {
+        // Empty reads may be positioned past the end of the stream
+        if size == 0 {
+            return Ok(Cow::Borrowed(&[]));
+        }
+
+        let start = self.offset + pos;
+        match &self.data {
+            StreamData::Borrowed(data) => Ok(Cow::Borrowed(&data[start..start + size])),
+            StreamData::Owned(data) => Ok(Cow::Borrowed(&data[start..start + size])),
+            StreamData::Reader(reader) => {
+                let mut bytes = vec![0; size];
+                reader.read_at(start as u64, &mut bytes)?;
+                Ok(Cow::Owned(bytes))
+            }
+        }
+    }
+
+    /// Reads the given number of bytes without aligning to a byte boundary
+    fn read_bytes_not_aligned(&mut self, size: usize) -> io::Result<Vec<u8>> {
+        if size > self.remaining() {
+            return Err(self.end_of_stream(Some(size)));
+        }
+
+        let bytes = self.read_at(self.pos, size)?.into_owned();
+        self.pos += size;
+        Ok(bytes)
+    }
+
+    /// Reads the given number of bytes
+    pub fn read_bytes(&mut self, size: usize) -> io::Result<Vec<u8>> {
+        self.align_to_byte();
+        self.read_bytes_not_aligned(size)
+    }
+
+    /// Reads all the bytes up to the end of the stream
+    pub fn read_bytes_full(&mut self) -> io::Result<Vec<u8>> {
+        self.read_bytes(self.remaining())
+    }
+
+    /// Reads the given number of bytes as a new stream positioned at their start
+    ///
+    /// The substream cannot read past these bytes, its positions are relative to its start.
+    /// It shares the data of this stream, the bytes are not copied nor read from a reader.
+    pub fn substream(&mut self, size: usize) -> io::Result<KaitaiStream<'a>> {
+        self.align_to_byte();
+        if size > self.remaining() {
+            return Err(self.end_of_stream(Some(size)));
+        }
+
+        let substream = KaitaiStream {
+            data: self.data.clone(),
+            offset: self.offset + self.pos,
+            size,
+            pos: 0,
+            bits: 0,
+            bits_left: 0,
+        };
+        self.pos += size;
+        Ok(substream)
+    }
+
+    /// Reads bytes up to the given terminator
+    ///
+    /// The terminator is a code unit of the data, e.g. two null bytes for UTF-16 strings, it is
+    /// only looked for at offsets that are multiples of its size from the current position.
+    /// `include` keeps the terminator in the returned bytes, `consume` moves the
+    /// position past it and `eos_error` fails if the end of the stream is reached
+    /// before the terminator is found
+    pub fn read_bytes_term(
+        &mut self,
+        terminator: &[u8],
+        include: bool,
+        consume: bool,
+        eos_error: bool,
+    ) -> io::Result<Vec<u8>> {
+        self.align_to_byte();
+
+        let (data, terminator_pos) = self.scan_terminator(terminator)?;
+        let (end, consumed) = match terminator_pos {
+            Some(terminator_pos) => (
+                if include {
+                    terminator_pos + terminator.len()
+                } else {
+                    terminator_pos
+                },
+                if consume {
+                    terminator_pos + terminator.len()
+                } else {
+                    terminator_pos
+                },
+            ),
+            None if eos_error => (0, 0),
+            None => (data.len(), data.len()),
+        };
+        let bytes = data[..end].to_vec();
+
+        // The bytes read from a reader past the consumed ones are read again by the next read
+        if let (StreamData::Reader(reader), Cow::Owned(data)) = (&self.data, &data) {
+            reader.unread(
+                (self.offset + self.pos + consumed) as u64,
+                &data[consumed..],
+            );
+        }
+        drop(data);
+
+        if terminator_pos.is_none() && eos_error {
+            return Err(self.end_of_stream(None));
+        }
+        self.pos += consumed;
+        Ok(bytes)
+    }
+
+    // Looks for a terminator from the current position, returning the bytes searched and the offset of the terminator
+    // Slices are searched in place, readers are read by growing chunks so that they are not read to their end
+    fn scan_terminator(&self, terminator: &[u8]) -> io::Result<(Cow<'_, [u8]>, Option<usize>)> {
+        if !matches!(self.data, StreamData::Reader(_)) {
+            let data = self.read_at(self.pos, self.remaining())?;
+            let terminator_pos = find_terminator(&data, terminator);
+            return Ok((data, terminator_pos));
+        }
+
+        let unit_size = terminator.len().max(1);
+        let mut bytes = Vec::new();
+        let mut searched = 0;
+        let mut chunk_size = TERMINATOR_FIRST_CHUNK_SIZE;
+        loop {
+            if let Some(terminator_pos) = find_terminator(&bytes[searched..], terminator) {
+                return Ok((Cow::Owned(bytes), Some(searched + terminator_pos)));
+            }
+            searched = bytes.len() - bytes.len() % unit_size;
+
+            let size = chunk_size.min(self.remaining() - bytes.len());
+            if size == 0 {
+                return Ok((Cow::Owned(bytes), None));
+            }
+            bytes.extend_from_slice(&self.read_at(self.pos + bytes.len(), size)?);
+            chunk_size = (chunk_size * 2).min(TERMINATOR_CHUNK_SIZE);
+        }
+    }
+
+    /// Reads an unsigned integer of `n` bits (up to 64), most significant bit first
+    pub fn read_bits_int_be(&mut self, n: u8) -> io::Result<u64> {
+        let bits_needed = n as i32 - self.bits_left as i32;
+        let mut result: u128;
+
+        // The bit state is only updated once the read succeeded, a failed read leaves the stream unchanged
+        let bits_left = (-bits_needed).rem_euclid(8) as u8;
+        if bits_needed > 0 {
+            // Read the missing bytes, they may contain more bits than needed
+            let bytes_needed = ((bits_needed - 1) / 8 + 1) as usize;
+            let bytes = self.read_bytes_not_aligned(bytes_needed)?;
+            let new_bits = bytes
+                .iter()
+                .fold(0u128, |value, &byte| (value << 8) | byte as u128);
+
+            result = (new_bits >> bits_left) | ((self.bits as u128) << bits_needed);
+            self.bits = (new_bits & ((1u128 << bits_left) - 1)) as u64;
+        } else {
+            // The left over bits are enough
+            result = (self.bits >> -bits_needed) as u128;
+            self.bits &= (1u64 << bits_left) - 1;
+        }
+
+        self.bits_left = bits_left;
+        result &= (1u128 << n) - 1;
+        Ok(result as u64)
+    }
+
+    /// Reads an unsigned integer of `n` bits (up to 64), least significant bit first
+    pub fn read_bits_int_le(&mut self, n: u8) -> io::Result<u64> {
+        let bits_needed = n as i32 - self.bits_left as i32;
+        let mut result: u128;
+
+        if bits_needed > 0 {
+            // Read the missing bytes, they may contain more bits than needed
+            let bytes_needed = ((bits_needed - 1) / 8 + 1) as usize;
+            let bytes = self.read_bytes_not_aligned(bytes_needed)?;
+            let new_bits = bytes
+                .iter()
+                .enumerate()
+                .fold(0u128, |value, (i, &byte)| value | (byte as u128) << (i * 8));
+
+            result = (new_bits << self.bits_left) | self.bits as u128;
+            self.bits = (new_bits >> bits_needed) as u64;
+        } else {
+            // The left over bits are enough
+            result = self.bits as u128;
+            self.bits = (self.bits as u128 >> n) as u64;
+        }
+
+        self.bits_left = (-bits_needed).rem_euclid(8) as u8;
+        result &= (1u128 << n) - 1;
+        Ok(result as u64)
+    }
+}
+
+/// Finds the offset of the first terminator in the data, looking only at offsets that are multiples of its size
+pub fn find_terminator(data: &[u8], terminator: &[u8]) -> Option<usize> {
+    if terminator.is_empty() {
+        return None;
+    }
+
+    data.chunks_exact(terminator.len())
+        .position(|unit| unit == terminator)
+        .map(|unit_index| unit_index * terminator.len())
+}
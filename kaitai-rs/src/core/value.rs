@@ -0,0 +1,398 @@
+use crate::core::ast::NodeRef;
+use crate::core::ast::NodeType;
+use crate::core::encoding;
+use crate::core::error::{DecodingError, ExpressionError};
+use crate::ks_language::language::kaitai_type::PureType;
+use crate::ks_language::parser::kaitai_type::parse_kaitai_type;
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+/// A typed value, as produced by the evaluation of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    Enum { enum_name: String, value: i128 },
+    Struct(NodeRef),
+}
+
+impl Value {
+    /// Creates an integer value, signed if it fits in an i64 and unsigned otherwise
+    pub fn from_integer(value: i128) -> Result<Value, ExpressionError> {
+        if let Ok(value) = i64::try_from(value) {
+            Ok(Value::Signed(value))
+        } else if let Ok(value) = u64::try_from(value) {
+            Ok(Value::Unsigned(value))
+        } else {
+            Err(ExpressionError::Overflow)
+        }
+    }
+
+    /// Converts a node of the AST into a value, according to its type
+    pub fn from_node(node_ref: &NodeRef) -> Result<Value, ExpressionError> {
+        let node = node_ref.borrow();
+        let absent = || ExpressionError::Absent(node.get_id().clone().unwrap_or_default());
+
+        // Integers of an enum are enum values
+        if let Some(enum_member) = node.get_enum_member() {
+            let value = match node.get_node_type() {
+                Some(NodeType::SignedInteger) => node.get_signed_integer().map(i128::from),
+                _ => node.get_unsigned_integer().map(i128::from),
+            };
+            return value
+                .map(|value| Value::Enum {
+                    enum_name: enum_member.enum_name.clone(),
+                    value,
+                })
+                .ok_or_else(absent);
+        }
+
+        match (node.get_node_type(), node.get_data()) {
+            (Some(NodeType::Integer), _) => node
+                .get_unsigned_integer()
+                .map(Value::Unsigned)
+                .ok_or_else(absent),
+            (Some(NodeType::SignedInteger), _) => node
+                .get_signed_integer()
+                .map(Value::Signed)
+                .ok_or_else(absent),
+            (Some(NodeType::Float), _) => node.get_float().map(Value::Float).ok_or_else(absent),
+            (Some(NodeType::Boolean), Some(data)) => {
+                Ok(Value::Bool(data.iter().any(|&byte| byte != 0)))
+            }
+            // Strings read by the engine keep their decoded text
+            (Some(NodeType::String), Some(data)) => Ok(Value::String(match node.get_string() {
+                Some(string) => string.to_string(),
+                None => String::from_utf8_lossy(data)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            })),
+            (Some(NodeType::Struct), _) => Ok(Value::Struct(node_ref.clone())),
+            (Some(NodeType::List), _) => node
+                .get_children()
+                .iter()
+                .map(Value::from_node)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            (Some(NodeType::Absent), _) | (_, None) => Err(absent()),
+            (_, Some(data)) => Ok(Value::Bytes(data.clone())),
+        }
+    }
+
+    /// Gets the name of the type of this value, used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Signed(_) | Value::Unsigned(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Bytes(_) => "byte array",
+            Value::Array(_) => "array",
+            Value::Enum { .. } => "enum",
+            Value::Struct(_) => "struct",
+        }
+    }
+
+    /// Gets this value as an integer, if it is one
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Value::Signed(value) => Some(*value as i128),
+            Value::Unsigned(value) => Some(*value as i128),
+            _ => None,
+        }
+    }
+
+    /// Gets this value as a float, if it is a number
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(value) => Some(*value),
+            _ => self.as_integer().map(|value| value as f64),
+        }
+    }
+
+    /// Converts this value to a boolean condition
+    pub fn to_bool(&self) -> Result<bool, ExpressionError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            _ => Err(ExpressionError::TypeMismatch(format!(
+                "expected a boolean, got {} {}",
+                self.type_name(),
+                self
+            ))),
+        }
+    }
+
+    /// Converts this value to a size or a count
+    pub fn to_usize(&self) -> Result<usize, ExpressionError> {
+        self.as_integer()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| {
+                ExpressionError::TypeMismatch(format!(
+                    "expected a non-negative integer, got {} {}",
+                    self.type_name(),
+                    self
+                ))
+            })
+    }
+
+    /// Checks whether two values are equal
+    /// Numbers are compared by value whatever their type, byte arrays can be compared to arrays of integers
+    pub fn equals(&self, other: &Value) -> Result<bool, ExpressionError> {
+        match (self, other) {
+            (Value::Bool(left), Value::Bool(right)) => Ok(left == right),
+            (Value::Array(left), Value::Array(right)) => {
+                if left.len() != right.len() {
+                    return Ok(false);
+                }
+                for (left, right) in left.iter().zip(right) {
+                    if !left.equals(right)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (Value::Bytes(bytes), array @ Value::Array(_))
+            | (array @ Value::Array(_), Value::Bytes(bytes)) => {
+                let bytes = bytes
+                    .iter()
+                    .map(|&byte| Value::Unsigned(byte as u64))
+                    .collect();
+                Value::Array(bytes).equals(array)
+            }
+            (
+                Value::Enum {
+                    enum_name: left_name,
+                    value: left,
+                },
+                Value::Enum {
+                    enum_name: right_name,
+                    value: right,
+                },
+            ) => Ok(left_name == right_name && left == right),
+            (Value::Struct(left), Value::Struct(right)) => Ok(Rc::ptr_eq(left, right)),
+            _ => self
+                .compare(other)
+                .map(|ordering| ordering == Ordering::Equal),
+        }
+    }
+
+    /// Compares two ordered values: numbers, strings or byte arrays
+    pub fn compare(&self, other: &Value) -> Result<Ordering, ExpressionError> {
+        let ordering = match (self, other) {
+            (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+            (Value::Bytes(left), Value::Bytes(right)) => Some(left.cmp(right)),
+            _ => match (self.as_integer(), other.as_integer()) {
+                (Some(left), Some(right)) => Some(left.cmp(&right)),
+                _ => match (self.as_float(), other.as_float()) {
+                    (Some(left), Some(right)) => left.partial_cmp(&right),
+                    _ => None,
+                },
+            },
+        };
+
+        ordering.ok_or_else(|| {
+            ExpressionError::TypeMismatch(format!(
+                "cannot compare {} {} with {} {}",
+                self.type_name(),
+                self,
+                other.type_name(),
+                other
+            ))
+        })
+    }
+
+    /// Calls a built-in method of this value, attributes without arguments such as `length` included
+    pub fn call_method(&self, name: &str, arguments: &[Value]) -> Result<Value, ExpressionError> {
+        match (self, name, arguments) {
+            (Value::Signed(_) | Value::Unsigned(_), "to_s", []) => {
+                Ok(Value::String(self.to_string()))
+            }
+            (Value::Float(value), "to_i", []) => {
+                if value.is_finite() {
+                    Value::from_integer(value.trunc() as i128)
+                } else {
+                    Err(ExpressionError::Overflow)
+                }
+            }
+            (Value::Bool(value), "to_i", []) => Ok(Value::Signed(*value as i64)),
+            (Value::Enum { value, .. }, "to_i", []) => Value::from_integer(*value),
+            (Value::String(string), "length", []) => {
+                Ok(Value::Signed(string.chars().count() as i64))
+            }
+            (Value::String(string), "reverse", []) => {
+                Ok(Value::String(string.chars().rev().collect()))
+            }
+            (Value::String(string), "to_i", []) => parse_integer(string, 10),
+            (Value::String(string), "to_i", [radix]) => {
+                let radix = radix
+                    .as_integer()
+                    .and_then(|radix| u32::try_from(radix).ok())
+                    .filter(|radix| (2..=36).contains(radix))
+                    .ok_or_else(|| {
+                        ExpressionError::TypeMismatch(format!("invalid radix {}", radix))
+                    })?;
+                parse_integer(string, radix)
+            }
+            (Value::String(string), "substring", [from, to]) => {
+                let length = string.chars().count();
+                let (from, to) = (from.to_usize()?, to.to_usize()?);
+                if to > length || from > to {
+                    return Err(ExpressionError::IndexOutOfBounds {
+                        index: to.max(from) as i128,
+                        length,
+                    });
+                }
+                Ok(Value::String(
+                    string.chars().skip(from).take(to - from).collect(),
+                ))
+            }
+            (Value::Bytes(bytes), "length", []) => Ok(Value::Signed(bytes.len() as i64)),
+            (Value::Bytes(bytes), "to_s", [Value::String(encoding)]) => {
+                decode(bytes, encoding).map(Value::String)
+            }
+            (Value::Bytes(bytes), "first" | "last" | "min" | "max", []) => {
+                let bytes = bytes
+                    .iter()
+                    .map(|&byte| Value::Unsigned(byte as u64))
+                    .collect();
+                Value::Array(bytes).call_method(name, arguments)
+            }
+            (Value::Array(values), "size", []) => Ok(Value::Signed(values.len() as i64)),
+            (Value::Array(values), "first" | "last" | "min" | "max", []) => {
+                let empty = || ExpressionError::IndexOutOfBounds {
+                    index: 0,
+                    length: 0,
+                };
+                let mut values = values.iter();
+                let mut result = match name {
+                    "last" => values.next_back(),
+                    _ => values.next(),
+                }
+                .ok_or_else(empty)?;
+
+                // Keep the smallest or the largest value
+                if name == "min" || name == "max" {
+                    let wanted = if name == "min" {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    };
+                    for value in values {
+                        if value.compare(result)? == wanted {
+                            result = value;
+                        }
+                    }
+                }
+                Ok(result.clone())
+            }
+            _ => Err(ExpressionError::Unsupported(format!(
+                "method '{}' with {} argument(s) on {} {}",
+                name,
+                arguments.len(),
+                self.type_name(),
+                self
+            ))),
+        }
+    }
+
+    /// Checks that this value is of the given type, for `.as<type>`
+    /// Integers are converted to floats, any other conversion is an error
+    pub fn cast(self, type_name: &str) -> Result<Value, ExpressionError> {
+        let pure_type = parse_kaitai_type(type_name)
+            .map(|kaitai_type| kaitai_type.pure_type)
+            .unwrap_or_else(|_| PureType::UserType(type_name.to_string()));
+
+        let compatible = match (&pure_type, &self) {
+            (
+                PureType::UnsignedInteger(_)
+                | PureType::SignedInteger(_)
+                | PureType::BitSizedInteger(_),
+                Value::Signed(_) | Value::Unsigned(_),
+            ) => true,
+            (PureType::FloatingPoint(_), Value::Float(_)) => true,
+            (PureType::FloatingPoint(_), _) => {
+                return self.as_float().map(Value::Float).ok_or_else(|| {
+                    ExpressionError::TypeMismatch(format!(
+                        "cannot cast {} {} to {}",
+                        self.type_name(),
+                        self,
+                        type_name
+                    ))
+                })
+            }
+            (PureType::Boolean, Value::Bool(_))
+            | (PureType::String | PureType::StringZ, Value::String(_))
+            | (PureType::ByteArray, Value::Bytes(_))
+            | (PureType::UserType(_) | PureType::ArbitraryStruct, Value::Struct(_))
+            | (PureType::AnyType, _) => true,
+            _ => false,
+        };
+
+        if compatible {
+            Ok(self)
+        } else {
+            Err(ExpressionError::TypeMismatch(format!(
+                "cannot cast {} {} to {}",
+                self.type_name(),
+                self,
+                type_name
+            )))
+        }
+    }
+}
+
+// Parses a string holding an integer in the given radix, for `to_i`
+fn parse_integer(string: &str, radix: u32) -> Result<Value, ExpressionError> {
+    i128::from_str_radix(string, radix)
+        .map_err(|_| {
+            ExpressionError::TypeMismatch(format!(
+                "cannot convert string {:?} to an integer in base {}",
+                string, radix
+            ))
+        })
+        .and_then(Value::from_integer)
+}
+
+// Decodes a byte array into a string, for `to_s(encoding)`
+fn decode(bytes: &[u8], encoding: &str) -> Result<String, ExpressionError> {
+    encoding::decode(bytes, encoding, false).map_err(|error| match error {
+        DecodingError::UnknownEncoding(_) => {
+            ExpressionError::Unsupported(format!("encoding '{}'", encoding))
+        }
+        DecodingError::InvalidBytes { .. } => ExpressionError::TypeMismatch(error.to_string()),
+    })
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Signed(value) => write!(f, "{}", value),
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{:?}", value),
+            Value::Bytes(value) => write!(f, "{:02x?}", value),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Enum { enum_name, value } => write!(f, "{}({})", enum_name, value),
+            Value::Struct(node) => match node.borrow().get_id() {
+                Some(id) => write!(f, "<{}>", id),
+                None => write!(f, "<struct>"),
+            },
+        }
+    }
+}
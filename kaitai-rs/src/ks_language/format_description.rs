@@ -1,3 +1,4 @@
+use crate::core::error::KaitaiError;
 use crate::ks_language::parser::parser::KSLanguageParser;
 
 use serde_yaml;
@@ -5,6 +6,7 @@ use std::fs;
 use std::io;
 use std::io::Read;
 use std::path::Path;
+use std::str::FromStr;
 
 // Define a struct `FormatDescription` that wraps the `KSLanguageParser`
 #[derive(Debug)]
@@ -12,7 +14,7 @@ pub struct FormatDescription {
     pub format: KSLanguageParser,
 }
 
-// Implement the `FormatDescription` struct with a `new` method and a `load_from_file` method
+// Implement the `FormatDescription` struct with a `new` method and the loaders of format descriptions
 impl FormatDescription {
     /// Create a new instance of `FormatDescription`
     pub fn new() -> Self {
@@ -22,28 +24,59 @@ impl FormatDescription {
     }
 
     /// Load the format description from a YAML file and return a `FormatDescription` instance
-    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> io::Result<FormatDescription> {
+    ///
+    /// Fails with a `KaitaiError` if the file cannot be read, is not valid YAML or does not
+    /// follow the Kaitai Struct schema
+    pub fn load_from_file<P: AsRef<Path>>(file_path: P) -> Result<FormatDescription, KaitaiError> {
         // Open the file.
         let file = fs::File::open(file_path)?;
 
         // Create a buffered reader to efficiently read the file
-        let mut reader = io::BufReader::new(file);
-
-        // Read the file content into a String
-        let mut content = String::new();
-        reader.read_to_string(&mut content)?;
-
-        // Parse the YAML content into serde_yaml::Value
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(&content)
-            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        Self::from_reader(io::BufReader::new(file))
+    }
 
-        // Create a new KSLanguageParser instance
+    /// Load the format description from a reader of YAML, e.g. a description stored in a database
+    pub fn from_reader<R: Read>(reader: R) -> Result<FormatDescription, KaitaiError> {
         let mut parser = KSLanguageParser::new();
+        parser.parse_yaml(reader)?;
+
+        Ok(FormatDescription { format: parser })
+    }
 
+    /// Load the format description from a parsed YAML document
+    pub fn from_yaml_value(
+        yaml_value: &serde_yaml::Value,
+    ) -> Result<FormatDescription, KaitaiError> {
         // Parse the YAML content using the `KSLanguageParser`
-        parser.parse_sections(&yaml_value)?;
+        let mut parser = KSLanguageParser::new();
+        parser.parse_sections(yaml_value)?;
 
         // Return a new FormatDescription instance with the parsed KSLanguageParser
         Ok(FormatDescription { format: parser })
     }
+
+    /// Load the format description from a JSON-encoded KSY document
+    pub fn from_json_str(json: &str) -> Result<FormatDescription, KaitaiError> {
+        let json_value: serde_json::Value = serde_json::from_str(json)?;
+        Self::from_json_value(&json_value)
+    }
+
+    /// Load the format description from a parsed JSON document
+    pub fn from_json_value(
+        json_value: &serde_json::Value,
+    ) -> Result<FormatDescription, KaitaiError> {
+        // JSON documents are YAML documents, the sections are parsed the same way
+        let yaml_value = serde_yaml::to_value(json_value)?;
+        Self::from_yaml_value(&yaml_value)
+    }
+}
+
+/// Load the format description from a YAML string, e.g. a description bundled with `include_str!`
+impl FromStr for FormatDescription {
+    type Err = KaitaiError;
+
+    fn from_str(yaml: &str) -> Result<Self, Self::Err> {
+        let yaml_value: serde_yaml::Value = serde_yaml::from_str(yaml)?;
+        Self::from_yaml_value(&yaml_value)
+    }
 }
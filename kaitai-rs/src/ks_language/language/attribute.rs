@@ -1,6 +1,6 @@
+use crate::core::expression::Expr;
 use crate::ks_language::language::doc::Doc;
 use crate::ks_language::language::doc_ref::DocRef;
-use crate::ks_language::language::enums::Enum;
 use crate::ks_language::language::kaitai_type::Type;
 
 // Attribute struct definition
@@ -20,37 +20,37 @@ pub struct Attribute {
     // Repeat settings for the attribute
     pub repeat: Option<Repeat>,
     // Expression for the number of repetitions
-    pub repeat_expr: Option<String>,
-    // TODO: Implement the repeat_until parser & engine
-    repeat_until: Option<String>,
+    pub repeat_expr: Option<Expr>,
+    // Condition ending the repetitions, checked after each element
+    pub repeat_until: Option<Expr>,
     // Mark the attribute as optional
-    optional_if: Option<String>,
+    pub optional_if: Option<Expr>,
     // Size of the attribute
-    pub size: Option<String>,
+    pub size: Option<Expr>,
     // Flag indicating whether size is until the end of the stream
     pub size_eos: bool,
     // Processing details for the attribute
-    process: Option<Process>,
-    // Enumeration associated with the attribute
-    attribute_enum: Option<Enum>,
-    // Encoding
-    encoding: Option<String>,
-    // Padding size to the right
-    pad_right: Option<u8>,
+    pub process: Option<Process>,
+    // Name of the enum the integer value of the attribute is an instance of, possibly qualified by the enclosing types
+    pub attribute_enum: Option<String>,
+    // Encoding of a string attribute, overriding the `meta/encoding` default
+    pub encoding: Option<String>,
+    // Padding byte stripped from the right of a sized byte array or string
+    pub pad_right: Option<u8>,
     // string or byte array reading will stop when it encounters this byte
     pub terminator: Option<u8>,
-    // Flag indicating whether to consume the attribute
-    consume: bool,
-    // Flag indicating whether to include the attribute in output
-    include: bool,
-    // Flag indicating whether an end-of-stream error should be raised
-    eos_error: bool,
-    // Position of the attribute in the stream
-    pos: Option<String>,
-    // Input/output association for the attribute
-    io: Option<String>,
-    // Value of the attribute
-    value: Option<String>,
+    // Flag indicating whether to move past the terminator after reading
+    pub consume: bool,
+    // Flag indicating whether to keep the terminator in the bytes read
+    pub include: bool,
+    // Flag indicating whether an end-of-stream error should be raised if the terminator is not found
+    pub eos_error: bool,
+    // Position of an instance in the stream
+    pub pos: Option<Expr>,
+    // Stream an instance is read from
+    pub io: Option<Expr>,
+    // Expression computing a value instance
+    pub value: Option<Expr>,
 }
 
 impl Attribute {
@@ -62,22 +62,22 @@ impl Attribute {
         contents: Option<Vec<u8>>,
         seq_type: Option<Type>,
         repeat: Option<Repeat>,
-        repeat_expr: Option<String>,
-        repeat_until: Option<String>,
-        optional_if: Option<String>,
-        size: Option<String>,
+        repeat_expr: Option<Expr>,
+        repeat_until: Option<Expr>,
+        optional_if: Option<Expr>,
+        size: Option<Expr>,
         size_eos: bool,
         process: Option<Process>,
-        attribute_enum: Option<Enum>,
+        attribute_enum: Option<String>,
         encoding: Option<String>,
         pad_right: Option<u8>,
         terminator: Option<u8>,
         consume: bool,
         include: bool,
         eos_error: bool,
-        pos: Option<String>,
-        io: Option<String>,
-        value: Option<String>,
+        pos: Option<Expr>,
+        io: Option<Expr>,
+        value: Option<Expr>,
     ) -> Self {
         Attribute {
             id,
@@ -118,19 +118,19 @@ impl Attribute {
         self.repeat = Some(repeat);
     }
 
-    pub fn set_repeat_expr(&mut self, repeat_expr: String) {
+    pub fn set_repeat_expr(&mut self, repeat_expr: Expr) {
         self.repeat_expr = Some(repeat_expr);
     }
 
-    pub fn set_repeat_until(&mut self, repeat_until: String) {
+    pub fn set_repeat_until(&mut self, repeat_until: Expr) {
         self.repeat_until = Some(repeat_until);
     }
 
-    pub fn set_optional_if(&mut self, optional_if: String) {
+    pub fn set_optional_if(&mut self, optional_if: Expr) {
         self.optional_if = Some(optional_if);
     }
 
-    pub fn set_size(&mut self, size: String) {
+    pub fn set_size(&mut self, size: Expr) {
         self.size = Some(size);
     }
 
@@ -142,7 +142,7 @@ impl Attribute {
         self.process = Some(process);
     }
 
-    pub fn set_attribute_enum(&mut self, attribute_enum: Enum) {
+    pub fn set_attribute_enum(&mut self, attribute_enum: String) {
         self.attribute_enum = Some(attribute_enum);
     }
 
@@ -170,15 +170,15 @@ impl Attribute {
         self.eos_error = eos_error;
     }
 
-    pub fn set_pos(&mut self, pos: String) {
+    pub fn set_pos(&mut self, pos: Expr) {
         self.pos = Some(pos);
     }
 
-    pub fn set_io(&mut self, io: String) {
+    pub fn set_io(&mut self, io: Expr) {
         self.io = Some(io);
     }
 
-    pub fn set_value(&mut self, value: String) {
+    pub fn set_value(&mut self, value: Expr) {
         self.value = Some(value);
     }
 }
@@ -205,6 +205,31 @@ pub enum ProcessType {
     Rol,
     // Right circular shift
     Ror,
+    // Routine registered by the application under the given name, e.g. `my_company.decrypt`
+    Custom(String),
+}
+
+impl ProcessType {
+    /// Gets the name of the processing type as written in a `process` key
+    pub fn name(&self) -> &str {
+        match self {
+            ProcessType::Zlib => "zlib",
+            ProcessType::Xor => "xor",
+            ProcessType::Rol => "rol",
+            ProcessType::Ror => "ror",
+            ProcessType::Custom(name) => name,
+        }
+    }
+
+    /// Describes the arguments expected by the processing type, for error messages
+    pub fn expected_arguments(&self) -> &'static str {
+        match self {
+            ProcessType::Zlib => "no arguments",
+            ProcessType::Xor => "a key",
+            ProcessType::Rol | ProcessType::Ror => "an amount and an optional group size of 1",
+            ProcessType::Custom(_) => "the arguments of the registered routine",
+        }
+    }
 }
 
 // Process struct definition
@@ -212,6 +237,6 @@ pub enum ProcessType {
 pub struct Process {
     // Type of processing to be applied
     pub process_type: ProcessType,
-    // TODO: Resolve the parameter
-    pub parameter: String,
+    // Arguments of the processing, evaluated when the attribute is parsed
+    pub arguments: Vec<Expr>,
 }
@@ -26,18 +26,39 @@ impl Enums {
         self.enums_specs.insert(identifier, enum_instance);
         Ok(())
     }
+
+    /// Gets the Enum with the given name, if any
+    pub fn get_enum(&self, name: &str) -> Option<&Enum> {
+        self.enums_specs
+            .iter()
+            .find(|(identifier, _)| identifier.get_values().iter().any(|value| value == name))
+            .map(|(_, enum_instance)| enum_instance)
+    }
 }
 
 /// Structure representing an Enum in a Kaitai Struct.
 #[derive(Debug)]
 pub struct Enum {
-    /// HashMap containing the possible values of the Enum.
-    pub values: HashMap<u32, String>,
+    /// HashMap containing the possible values of the Enum, associated with their member names.
+    pub values: HashMap<i128, String>,
 }
 
 impl Enum {
     /// Creates a new instance of `Enum` with the specified values.
-    pub fn new(values: HashMap<u32, String>) -> Result<Self, io::Error> {
+    pub fn new(values: HashMap<i128, String>) -> Result<Self, io::Error> {
         Ok(Enum { values })
     }
+
+    /// Gets the name of the member with the given value, if any
+    pub fn get_member(&self, value: i128) -> Option<&str> {
+        self.values.get(&value).map(String::as_str)
+    }
+
+    /// Gets the value of the member with the given name, if any
+    pub fn get_value(&self, member: &str) -> Option<i128> {
+        self.values
+            .iter()
+            .find(|(_, name)| name.as_str() == member)
+            .map(|(value, _)| *value)
+    }
 }
@@ -15,8 +15,7 @@ impl Identifier {
     }
 
     // Getter method to retrieve identifier values
-    #[allow(dead_code)]
-    fn get_values(&self) -> &Vec<String> {
+    pub fn get_values(&self) -> &Vec<String> {
         &self.value
     }
 
@@ -26,7 +25,7 @@ impl Identifier {
         let values: Vec<String> = vec![identifier.to_string()];
 
         // Check if all values match the identifier pattern
-        validate_values(&values, Config::IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::IDENTIFIER_PATTERN)?;
         self.value = values;
 
         Ok(())
@@ -37,7 +36,7 @@ impl Identifier {
         let values: Vec<String> = identifiers;
 
         // Check if all values match the identifier pattern
-        validate_values(&values, Config::IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::IDENTIFIER_PATTERN)?;
         self.value = values;
 
         Ok(())
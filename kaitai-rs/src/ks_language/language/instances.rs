@@ -1,25 +1,38 @@
 use crate::ks_language::language::attribute::Attribute;
 use crate::ks_language::language::identifier::Identifier;
-use std::collections::HashMap;
 
 /// Instances struct representing a collection of instances
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Instances {
-    // Hashmap of instances, where each identifier is associated with an attribute
-    instances_spec: HashMap<Identifier, Attribute>,
+    // Instances, where each identifier is associated with an attribute, in the order of the definition
+    instances_spec: Vec<(Identifier, Attribute)>,
 }
 
 impl Instances {
     /// Constructor function to create a new Instances struct
     pub fn new() -> Self {
         Instances {
-            instances_spec: HashMap::new(),
+            instances_spec: Vec::new(),
         }
     }
 
     // Method to add an attribute to the instances
     pub fn add_attribute(&mut self, identifier: Identifier, attribute: Attribute) {
-        self.instances_spec.insert(identifier, attribute);
+        self.instances_spec.push((identifier, attribute));
+    }
+
+    /// Gets the attribute of the instance with the given name, if any
+    pub fn get_instance(&self, name: &str) -> Option<&Attribute> {
+        self.iter()
+            .find(|(identifier, _)| identifier.get_values().iter().any(|value| value == name))
+            .map(|(_, attribute)| attribute)
+    }
+
+    /// Iterates over the instances, in the order of the definition
+    pub fn iter(&self) -> impl Iterator<Item = (&Identifier, &Attribute)> {
+        self.instances_spec
+            .iter()
+            .map(|(identifier, attribute)| (identifier, attribute))
     }
 }
@@ -1,3 +1,6 @@
+use crate::core::expression::Expr;
+use crate::ks_language::language::meta::EndianEnum;
+
 // Enumeration representing all existing types
 #[derive(Debug)]
 pub enum PureType {
@@ -9,10 +12,11 @@ pub enum PureType {
     String,
     StringZ,
     Boolean,
-    UserType(String), // Placeholder for user-defined type
+    UserType(String), // User-defined type, resolved by name in the `types` sections
     ArbitraryStruct,
     IOStream,
     AnyType,
+    Switch(SwitchType), // Type selected at parse time from the value of an expression
 }
 
 // Type structure
@@ -22,35 +26,54 @@ pub struct Type {
     pub pure_type: PureType,
     // Indicates whether the type is an array
     pub is_array: bool,
+    // Endianness given by the type suffix (`le`/`be`), if any
+    pub endian: Option<EndianEnum>,
+}
+
+// Switch type structure (`switch-on` / `cases`)
+#[derive(Debug)]
+pub struct SwitchType {
+    // Expression whose value selects the case
+    pub switch_on: Expr,
+    // Cases, as pairs of a case expression and the type it selects
+    pub cases: Vec<(Expr, Type)>,
+    // Type selected when no case matches (`_` case)
+    pub default: Option<Box<Type>>,
 }
 
-/// Parses an unsigned integer of the given size and returns a Vec<u8> of the specified size
-pub fn parse_unsigned_integer(data: &[u8], size: usize) -> Vec<u8> {
-    let mut value = 0;
-    for i in 0..size {
-        value |= (data[i] as u64) << (i * 8);
+/// Parses an unsigned integer of the given size (1 to 8 bytes) with the given endianness
+pub fn parse_unsigned_integer(data: &[u8], size: usize, endian: EndianEnum) -> u64 {
+    // Data shorter than the size is read as a smaller integer instead of failing
+    let bytes = &data[..size.min(data.len())];
+    match endian {
+        EndianEnum::Le => bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| (value << 8) | byte as u64),
+        EndianEnum::Be => bytes
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | byte as u64),
     }
-    let mut result = vec![0; size];
-    result.copy_from_slice(&value.to_le_bytes()[..size]);
-    result
 }
 
-/// Parses a null-terminated string (or with a custom terminator)
-/// Returns the parsed string as a Vec<u8> up to and including the terminator if size is not specified,
-/// or up to the specified size if provided, ignoring the terminator
-pub fn parse_strz(data: &[u8], size: Option<usize>, terminator: u8) -> Vec<u8> {
-    let end_pos = if let Some(specified_size) = size {
-        // Use the specified size, ignoring the terminator
-        std::cmp::min(specified_size, data.len())
-    } else {
-        // No size specified, find the terminator position
-        if let Some(terminator_pos) = data.iter().position(|&x| x == terminator) {
-            terminator_pos + 1 // Include the terminator
-        } else {
-            data.len() // Default to the entire length of the data
-        }
-    };
+/// Parses a two's complement signed integer of the given size (1 to 8 bytes) with the given endianness
+pub fn parse_signed_integer(data: &[u8], size: usize, endian: EndianEnum) -> i64 {
+    let value = parse_unsigned_integer(data, size, endian);
 
-    // Return the string up to end_pos
-    data[..end_pos].to_vec()
+    // Sign-extend the value from its most significant bit
+    let bits = size.min(data.len()) * 8;
+    if bits == 0 || bits >= 64 {
+        return value as i64;
+    }
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// Parses an IEEE-754 floating-point number of the given size (4 or 8 bytes) with the given endianness
+pub fn parse_float(data: &[u8], size: usize, endian: EndianEnum) -> f64 {
+    let bits = parse_unsigned_integer(data, size, endian);
+    match size {
+        4 => f32::from_bits(bits as u32) as f64,
+        _ => f64::from_bits(bits),
+    }
 }
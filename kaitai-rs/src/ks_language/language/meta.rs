@@ -30,6 +30,8 @@ pub struct Meta {
     encoding: Option<String>,
     // Endian used in the KS (le/be)
     endian: Option<Endian>,
+    // Bit endian used for the bit-sized integers in the KS (le/be)
+    bit_endian: Option<Endian>,
 }
 
 impl Meta {
@@ -48,6 +50,7 @@ impl Meta {
             imports: None,
             encoding: None,
             endian: None,
+            bit_endian: None,
         }
     }
 
@@ -81,9 +84,25 @@ impl Meta {
         self.endian = Some(Endian { endian });
     }
 
-    // Set imports for Meta instance
-    pub fn set_imports(&mut self, values: Vec<String>) {
-        self.imports = Some(Imports::new(values).unwrap());
+    // Get the default endianness of the Meta instance, if any
+    pub fn get_endian(&self) -> Option<EndianEnum> {
+        self.endian.as_ref().map(|endian| endian.endian)
+    }
+
+    // Set bit endianness for Meta instance
+    pub fn set_bit_endian(&mut self, bit_endian: EndianEnum) {
+        self.bit_endian = Some(Endian { endian: bit_endian });
+    }
+
+    // Get the default bit endianness of the Meta instance, if any
+    pub fn get_bit_endian(&self) -> Option<EndianEnum> {
+        self.bit_endian.as_ref().map(|bit_endian| bit_endian.endian)
+    }
+
+    // Set imports for Meta instance, the values must be valid import paths
+    pub fn set_imports(&mut self, values: Vec<String>) -> Result<(), io::Error> {
+        self.imports = Some(Imports::new(values)?);
+        Ok(())
     }
 
     // Set encoding for Meta instance
@@ -91,6 +110,11 @@ impl Meta {
         self.encoding = Some(encoding);
     }
 
+    // Get the default encoding of the Meta instance, if any
+    pub fn get_encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
+
     // Set file extensions for Meta instance
     pub fn set_file_extension(&mut self, values: Vec<String>) {
         self.file_extension = Some(FileExtension::new(values));
@@ -147,7 +171,7 @@ pub struct ForensicWiki {
 impl ForensicWiki {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the media wiki page pattern
-        validate_values(&values, Config::MEDIA_WIKI_PAGE_NAME_PATTERN).unwrap();
+        validate_values(&values, Config::MEDIA_WIKI_PAGE_NAME_PATTERN)?;
 
         Ok(ForensicWiki { value: values })
     }
@@ -168,7 +192,7 @@ pub struct ISO {
 impl ISO {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the ISO pattern
-        validate_values(&values, Config::ISO_IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::ISO_IDENTIFIER_PATTERN)?;
 
         Ok(ISO { value: values })
     }
@@ -189,7 +213,7 @@ pub struct JustSolve {
 impl JustSolve {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the media wiki page pattern
-        validate_values(&values, Config::MEDIA_WIKI_PAGE_NAME_PATTERN).unwrap();
+        validate_values(&values, Config::MEDIA_WIKI_PAGE_NAME_PATTERN)?;
 
         Ok(JustSolve { value: values })
     }
@@ -210,7 +234,7 @@ pub struct LocIdentifier {
 impl LocIdentifier {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the loc identifier pattern
-        validate_values(&values, Config::LOC_IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::LOC_IDENTIFIER_PATTERN)?;
 
         Ok(LocIdentifier { value: values })
     }
@@ -231,7 +255,7 @@ pub struct MIMEType {
 impl MIMEType {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the MIME type pattern
-        validate_values(&values, Config::MIME_TYPE_PATTERN).unwrap();
+        validate_values(&values, Config::MIME_TYPE_PATTERN)?;
 
         Ok(MIMEType { value: values })
     }
@@ -252,7 +276,7 @@ pub struct PronomIdentifier {
 impl PronomIdentifier {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the Pronom identifier pattern
-        validate_values(&values, Config::PRONOM_IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::PRONOM_IDENTIFIER_PATTERN)?;
 
         Ok(PronomIdentifier { value: values })
     }
@@ -273,7 +297,7 @@ pub struct RFCIdentifier {
 impl RFCIdentifier {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the RFC identifier pattern
-        validate_values(&values, Config::RFC_IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::RFC_IDENTIFIER_PATTERN)?;
 
         Ok(RFCIdentifier { value: values })
     }
@@ -294,7 +318,7 @@ pub struct WikiDataIdentifier {
 impl WikiDataIdentifier {
     pub fn new(values: Vec<String>) -> Result<Self, io::Error> {
         // Check if all values match the WikiData identifier pattern
-        validate_values(&values, Config::WIKI_DATA_IDENTIFIER_PATTERN).unwrap();
+        validate_values(&values, Config::WIKI_DATA_IDENTIFIER_PATTERN)?;
 
         Ok(WikiDataIdentifier { value: values })
     }
@@ -390,7 +414,7 @@ struct Imports {
 
 impl Imports {
     fn new(values: Vec<String>) -> Result<Self, io::Error> {
-        validate_values(&values, Config::IMPORT_PATTERN).unwrap();
+        validate_values(&values, Config::IMPORT_PATTERN)?;
 
         Ok(Imports { value: values })
     }
@@ -403,7 +427,7 @@ impl Imports {
 }
 
 // Enum to represent the possible endian types
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EndianEnum {
     Le,
     Be,
@@ -411,7 +435,6 @@ pub enum EndianEnum {
 
 // Define the Endian struct to represent endian information
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct Endian {
     endian: EndianEnum,
 }
@@ -2,13 +2,13 @@ use crate::ks_language::language::doc::Doc;
 use crate::ks_language::language::doc_ref::DocRef;
 use crate::ks_language::language::enums::Enums;
 use crate::ks_language::language::identifier::Identifier;
+use crate::ks_language::language::instances::Instances;
 use crate::ks_language::language::meta::Meta;
 use crate::ks_language::language::params::Params;
 use crate::ks_language::language::seq::Seq;
 use std::collections::HashMap;
 
 /// Types struct representing a collection of type specifications
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Types {
     // HashMap mapping identifiers to type specifications
@@ -32,6 +32,22 @@ impl Types {
         self.types.insert(identifier, typespec_instance);
         Ok(())
     }
+
+    /// Gets the TypeSpec with the given name, if any
+    pub fn get_typespec(&self, name: &str) -> Option<&TypeSpec> {
+        self.get_typespec_entry(name).map(|(_, typespec)| typespec)
+    }
+
+    /// Gets the TypeSpec with the given name along with its name as defined, if any
+    pub fn get_typespec_entry(&self, name: &str) -> Option<(&str, &TypeSpec)> {
+        self.types.iter().find_map(|(identifier, typespec)| {
+            identifier
+                .get_values()
+                .iter()
+                .find(|value| *value == name)
+                .map(|value| (value.as_str(), typespec))
+        })
+    }
 }
 
 // TypeSpec struct representing a type specification
@@ -48,8 +64,8 @@ pub struct TypeSpec {
     pub type_types: Types,
     // Enumerations associated with the type
     pub type_enums: Enums,
-    // TODO: Replace with instance struct
-    pub instances: String,
+    // Instances associated with the type
+    pub instances: Instances,
     // Documentation for the type
     pub doc: Doc,
     // Reference to external documentation
@@ -63,7 +79,7 @@ impl TypeSpec {
         seq: Seq,
         type_types: Types,
         type_enums: Enums,
-        instances: String,
+        instances: Instances,
         doc: Doc,
         doc_ref: DocRef,
     ) -> Self {
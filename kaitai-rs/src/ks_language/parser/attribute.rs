@@ -1,3 +1,5 @@
+use crate::config::Config;
+use crate::core::error::KaitaiError;
 use crate::ks_language::language::attribute::Attribute;
 use crate::ks_language::language::attribute::Process;
 use crate::ks_language::language::attribute::ProcessType;
@@ -6,12 +8,15 @@ use crate::ks_language::language::doc::Doc;
 use crate::ks_language::language::doc_ref::DocRef;
 use crate::ks_language::parser::doc::parse_doc;
 use crate::ks_language::parser::doc_ref::parse_doc_ref;
-use crate::ks_language::parser::kaitai_type::parse_kaitai_type;
+use crate::ks_language::parser::expression::compile_expression;
+use crate::ks_language::parser::kaitai_type::{parse_kaitai_type, parse_switch_type};
+use crate::utils::validate_values;
 use serde_yaml::Value;
 use std::io;
 
 // Parses an attribute from a Kaitai Struct definition and returns it
-pub fn parse_attribute(attribute: &Value) -> Result<Attribute, io::Error> {
+// Errors are located by the key of the invalid field, e.g. `/size`
+pub fn parse_attribute(attribute: &Value) -> Result<Attribute, KaitaiError> {
     // Check if the "id" field exists before parsing it
     let identifier = if let Some(id_value) = attribute.get("id").and_then(|value| value.as_str()) {
         Some(id_value.to_string())
@@ -22,7 +27,8 @@ pub fn parse_attribute(attribute: &Value) -> Result<Attribute, io::Error> {
     // Check if the "doc" field exists and parse it if it does
     let doc = if let Some(doc_value) = attribute.get("doc") {
         let mut doc = Doc::new();
-        parse_doc(&mut doc, doc_value)?;
+        parse_doc(&mut doc, doc_value)
+            .map_err(|error| KaitaiError::from(error).in_schema("doc"))?;
         Some(doc)
     } else {
         None
@@ -31,24 +37,25 @@ pub fn parse_attribute(attribute: &Value) -> Result<Attribute, io::Error> {
     // Check if the "doc_ref" field exists and parse it if it does
     let doc_ref = if let Some(doc_ref_value) = attribute.get("doc_ref") {
         let mut doc_ref = DocRef::new();
-        parse_doc_ref(&mut doc_ref, doc_ref_value)?;
+        parse_doc_ref(&mut doc_ref, doc_ref_value)
+            .map_err(|error| KaitaiError::from(error).in_schema("doc_ref"))?;
         Some(doc_ref)
     } else {
         None
     };
 
     // Check if the "seq_type" field exists and parse it if it does
-    let seq_type = if let Some(seq_type_value) = attribute.get("type") {
-        let seq_type_str = seq_type_value.as_str().ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid 'seq_type' field. Expected a string.",
-            )
-        })?;
-        Some(parse_kaitai_type(seq_type_str)?)
-    } else {
-        None
-    };
+    let seq_type = match attribute.get("type") {
+        Some(Value::String(seq_type_str)) => Some(parse_kaitai_type(seq_type_str)),
+        Some(seq_type_value @ Value::Mapping(_)) => Some(parse_switch_type(seq_type_value)),
+        Some(_) => Some(Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid 'seq_type' field. Expected a string or a switch-on mapping.",
+        ))),
+        None => None,
+    }
+    .transpose()
+    .map_err(|error| KaitaiError::from(error).in_schema("type"))?;
 
     // Create a new Attribute instance with the parsed values
     let mut new_attribute = Attribute::new(
@@ -60,7 +67,8 @@ pub fn parse_attribute(attribute: &Value) -> Result<Attribute, io::Error> {
     macro_rules! parse_attribute_field {
         ($attr:expr, $field_name:literal, $func:ident) => {
             if let Some(value) = $attr.get($field_name) {
-                $func(&mut new_attribute, value)?;
+                $func(&mut new_attribute, value)
+                    .map_err(|error| KaitaiError::from(error).in_schema($field_name))?;
             }
         };
     }
@@ -74,13 +82,14 @@ pub fn parse_attribute(attribute: &Value) -> Result<Attribute, io::Error> {
     parse_attribute_field!(attribute, "size", parse_size);
     parse_attribute_field!(attribute, "size-eos", parse_size_eos);
     parse_attribute_field!(attribute, "process", parse_process);
-    // TODO: Parse the "enum" field
+    parse_attribute_field!(attribute, "enum", parse_attribute_enum);
     parse_attribute_field!(attribute, "encoding", parse_encoding);
     parse_attribute_field!(attribute, "pad-right", parse_pad_right);
     parse_attribute_field!(attribute, "terminator", parse_terminator);
     parse_attribute_field!(attribute, "consume", parse_consume);
     parse_attribute_field!(attribute, "include", parse_include);
     parse_attribute_field!(attribute, "eos-error", parse_eos_error);
+    parse_attribute_field!(attribute, "pos", parse_pos);
     parse_attribute_field!(attribute, "io", parse_io);
     parse_attribute_field!(attribute, "value", parse_value);
 
@@ -118,13 +127,24 @@ pub fn parse_contents(
                         {
                             contents_vec.push(byte_val);
                         } else {
-                            // Panic if conversion to u8 fails
-                            panic!("Failed to convert value {:?} to u8", num);
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!(
+                                    "Invalid 'contents' field. Expected a byte, got {:?}.",
+                                    num
+                                ),
+                            ));
                         }
                     }
                     // Handle unsupported types in the sequence
                     _ => {
-                        panic!("Unsupported sequence element {:?}", value);
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "Invalid 'contents' field. Expected strings or bytes, got {:?}.",
+                                value
+                            ),
+                        ));
                     }
                 }
             }
@@ -151,13 +171,18 @@ pub fn parse_repeat(
     repeat_value: &Value,
 ) -> Result<(), io::Error> {
     let repeat = match repeat_value.as_str() {
-        Some("eos") => Some(Repeat::Eos),
-        Some("expr") => Some(Repeat::Expr),
-        Some("until") => Some(Repeat::Until),
-        _ => None,
+        Some("eos") => Repeat::Eos,
+        Some("expr") => Repeat::Expr,
+        Some("until") => Repeat::Until,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid 'repeat' field. Expected one of eos, expr or until.",
+            ))
+        }
     };
 
-    attribute_instance.set_repeat(repeat.unwrap());
+    attribute_instance.set_repeat(repeat);
     Ok(())
 }
 
@@ -185,7 +210,7 @@ pub fn parse_repeat_expr(
         }
     };
 
-    attribute_instance.set_repeat_expr(repeat_expr);
+    attribute_instance.set_repeat_expr(compile_expression("repeat-expr", &repeat_expr)?);
     Ok(())
 }
 
@@ -205,7 +230,7 @@ pub fn parse_repeat_until(
         }
     };
 
-    attribute_instance.set_repeat_until(repeat_until);
+    attribute_instance.set_repeat_until(compile_expression("repeat-until", &repeat_until)?);
     Ok(())
 }
 
@@ -225,7 +250,7 @@ pub fn parse_optional_if(
         }
     };
 
-    attribute_instance.set_optional_if(optional_if);
+    attribute_instance.set_optional_if(compile_expression("if", &optional_if)?);
     Ok(())
 }
 
@@ -250,7 +275,7 @@ pub fn parse_size(attribute_instance: &mut Attribute, size_value: &Value) -> Res
         }
     };
 
-    attribute_instance.set_size(size);
+    attribute_instance.set_size(compile_expression("size", &size)?);
     Ok(())
 }
 
@@ -274,42 +299,106 @@ pub fn parse_size_eos(
 }
 
 // Parses the "process" attribute of an Attribute instance from the provided Value
+// The value is the name of the routine followed by its arguments, e.g. `zlib` or `xor(key)`
 pub fn parse_process(
     attribute_instance: &mut Attribute,
     process_value: &Value,
 ) -> Result<(), io::Error> {
-    let process_type = match process_value.get("type").and_then(|value| value.as_str()) {
-        Some("zlib") => ProcessType::Zlib,
-        Some("xor") => ProcessType::Xor,
-        Some("rol") => ProcessType::Rol,
-        Some("ror") => ProcessType::Ror,
-        _ => {
+    let process_str = match process_value.as_str() {
+        Some(process_str) => process_str.trim(),
+        None => {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
-                "Invalid 'process' field. Expected an object with a 'type' field specifying the processing type.",
+                "Invalid 'process' field. Expected a string such as 'zlib' or 'xor(key)'.",
             ))
         }
     };
+    validate_values(&[process_str.to_string()], Config::PROCESS_PATTERN).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Invalid 'process' field: '{}'. Expected a routine name such as zlib, xor(key) or my_company.decrypt(key).",
+                process_str
+            ),
+        )
+    })?;
+
+    let (name, arguments) = match process_str.split_once('(') {
+        Some((name, arguments)) => (name, arguments.strip_suffix(')').unwrap_or(arguments)),
+        None => (process_str, ""),
+    };
 
-    let process_parameter = match process_value.get("parameter").and_then(|value| value.as_str()) {
-        Some(parameter) => parameter.to_string(),
-        None => {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid 'process' field. Expected an object with a 'parameter' field specifying the processing parameter.",
-            ))
-        }
+    let process_type = match name {
+        "zlib" => ProcessType::Zlib,
+        "xor" => ProcessType::Xor,
+        "rol" => ProcessType::Rol,
+        "ror" => ProcessType::Ror,
+        // Custom routines are looked up by name when the attribute is parsed
+        name => ProcessType::Custom(name.to_string()),
     };
 
+    let arguments = split_arguments(arguments)
+        .into_iter()
+        .map(|argument| compile_expression("process", argument))
+        .collect::<Result<Vec<_>, _>>()?;
+
     let process = Process {
         process_type,
-        parameter: process_parameter,
+        arguments,
     };
 
     attribute_instance.set_process(process);
     Ok(())
 }
 
+// Splits the arguments of a process on the commas that are not nested in brackets, parentheses or strings
+fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (index, character) in arguments.char_indices() {
+        match (quote, character) {
+            (Some(open), _) if character == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(character),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                result.push(arguments[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let last = arguments[start..].trim();
+    if !last.is_empty() || !result.is_empty() {
+        result.push(last);
+    }
+    result
+}
+
+// Parses the "enum" attribute of an Attribute instance from the provided Value
+pub fn parse_attribute_enum(
+    attribute_instance: &mut Attribute,
+    enum_value: &Value,
+) -> Result<(), io::Error> {
+    let attribute_enum = match enum_value.as_str() {
+        Some(s) => s.to_string(),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid 'enum' field. Expected a string.",
+            ))
+        }
+    };
+
+    attribute_instance.set_attribute_enum(attribute_enum);
+    Ok(())
+}
+
 // Parses the "encoding" attribute of an Attribute instance from the provided Value
 pub fn parse_encoding(
     attribute_instance: &mut Attribute,
@@ -445,7 +534,7 @@ pub fn parse_pos(attribute_instance: &mut Attribute, pos_value: &Value) -> Resul
         }
     };
 
-    attribute_instance.set_pos(pos);
+    attribute_instance.set_pos(compile_expression("pos", &pos)?);
     Ok(())
 }
 
@@ -461,7 +550,7 @@ pub fn parse_io(attribute_instance: &mut Attribute, io_value: &Value) -> Result<
         }
     };
 
-    attribute_instance.set_io(io);
+    attribute_instance.set_io(compile_expression("io", &io)?);
     Ok(())
 }
 
@@ -472,13 +561,13 @@ pub fn parse_value(
 ) -> Result<(), io::Error> {
     // Attempt to parse value as an integer
     if let Some(int_value) = value_value.as_i64() {
-        attribute_instance.set_value(int_value.to_string());
+        attribute_instance.set_value(compile_expression("value", &int_value.to_string())?);
         return Ok(());
     }
 
     // If parsing as an integer fails, treat it as a string
     if let Some(str_value) = value_value.as_str() {
-        attribute_instance.set_value(str_value.to_string());
+        attribute_instance.set_value(compile_expression("value", str_value)?);
         return Ok(());
     }
 
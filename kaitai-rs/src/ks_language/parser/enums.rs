@@ -32,9 +32,15 @@ fn parse_enum(
     if let Value::Mapping(variant_map) = enum_values {
         // Create an Identifier for the Enum
         let mut enum_identifier = Identifier::new();
+        let enum_name = enum_name.as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid enum name in enums section. Expected a string.",
+            )
+        })?;
 
         enum_identifier
-            .from_string_vec(vec![enum_name.as_str().unwrap().to_string()])
+            .from_string_vec(vec![enum_name.to_string()])
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
 
         // Create an Enum instance to store parsed variant values
@@ -60,31 +66,49 @@ fn parse_enum(
 }
 
 /// Parses an individual Enum variant within the "enums" section of the Kaitai Struct definition.
+///
+/// A variant associates an integer with either a member name or a mapping holding it:
+///   - 1: cat
+///   - 2: { id: dog, doc: "A dog" }
 fn parse_enum_variant(
     enum_instance: &mut Enum,
     variant_value: &Value,
     variant_name: &Value,
 ) -> Result<(), io::Error> {
-    // If "variant_value" is a number and "variant_name" is a string, proceed with parsing
-    if let (Value::Number(value), Value::String(description)) = (variant_value, variant_name) {
-        // Extract the variant value as u32
-        let variant_value = value.as_u64().ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Variant value is not a valid u32",
-            )
-        })? as u32;
-
-        // Insert the variant into the Enum's values HashMap
-        enum_instance
-            .values
-            .insert(variant_value, description.to_string());
-        Ok(())
-    } else {
-        // If the types are not as expected, return an error
-        Err(io::Error::new(
+    // Keys may be negative or larger than 32 bits
+    let value = match variant_value {
+        Value::Number(value) => value
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| value.as_u64().map(i128::from)),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        io::Error::new(
             io::ErrorKind::InvalidData,
-            "Invalid data in enum variant",
-        ))
+            format!(
+                "Invalid enum variant value {:?}. Expected an integer.",
+                variant_value
+            ),
+        )
+    })?;
+
+    let name = match variant_name {
+        Value::String(name) => Some(name.as_str()),
+        Value::Mapping(_) => variant_name.get("id").and_then(Value::as_str),
+        _ => None,
     }
+    .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Invalid name for enum variant {}. Expected a string or a mapping with an 'id' field.",
+                value
+            ),
+        )
+    })?;
+
+    // Insert the variant into the Enum's values HashMap
+    enum_instance.values.insert(value, name.to_string());
+    Ok(())
 }
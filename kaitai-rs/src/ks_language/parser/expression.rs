@@ -0,0 +1,15 @@
+use crate::core::expression::{parse_expression, Expr};
+use std::io;
+
+/// Compiles the expression of a field into an expression tree
+///
+/// Expressions are compiled once, when the format description is loaded, so that
+/// syntax errors are reported then and the parsing only evaluates the trees
+pub fn compile_expression(field_name: &str, expr: &str) -> Result<Expr, io::Error> {
+    parse_expression(expr).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid '{}' field: {}", field_name, error),
+        )
+    })
+}
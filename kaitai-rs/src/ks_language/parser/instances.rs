@@ -1,3 +1,4 @@
+use crate::core::error::KaitaiError;
 use crate::ks_language::language::identifier::Identifier;
 use crate::ks_language::language::instances::Instances;
 use crate::ks_language::parser::attribute::parse_attribute;
@@ -10,16 +11,20 @@ use std::io;
 pub fn parse_instances(
     instances_instance: &mut Instances,
     instances: &Value,
-) -> Result<(), io::Error> {
+) -> Result<(), KaitaiError> {
     if let Value::Mapping(instance_map) = instances {
         for (id_value, attr_value) in instance_map {
             let id_str = id_value
                 .as_str()
                 .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid identifier"))?;
             let mut identifier = Identifier::new();
-            parse_identifier(&mut identifier, id_str)?;
+            parse_identifier(&mut identifier, id_str)
+                .map_err(|error| KaitaiError::from(error).in_schema(id_str))?;
 
-            let attribute = parse_attribute(attr_value)?;
+            // Instances are named by their key
+            let mut attribute =
+                parse_attribute(attr_value).map_err(|error| error.in_schema(id_str))?;
+            attribute.id = Some(id_str.to_string());
             instances_instance.add_attribute(identifier, attribute);
         }
     }
@@ -1,5 +1,12 @@
+use crate::config::Config;
+use crate::core::error::KaitaiError;
 use crate::ks_language::language::kaitai_type::PureType;
+use crate::ks_language::language::kaitai_type::SwitchType;
 use crate::ks_language::language::kaitai_type::Type;
+use crate::ks_language::language::meta::EndianEnum;
+use crate::ks_language::parser::expression::compile_expression;
+use crate::utils::validate_values;
+use serde_yaml::Value;
 use std::io;
 
 /// Parses a Kaitai type from a string representation.
@@ -12,13 +19,15 @@ pub fn parse_kaitai_type(type_str: &str) -> Result<Type, io::Error> {
         (type_str, false)
     };
 
-    let pure_type = if base_type.starts_with("bx") && base_type.len() > 2 {
-        // If it starts with "bx" and has a size greater than 1
-        let size: u8 = base_type[2..].parse().unwrap_or(0);
-        if size != 1 {
-            PureType::BitSizedInteger(size)
+    // Split the endianness suffix from multi-byte numeric and bit-sized types (e.g. "u4be", "f8le", "b12le")
+    let (base_type, endian) = split_endian_suffix(base_type);
+
+    let pure_type = if let Some(size) = parse_bit_size(base_type) {
+        // Bit-sized integers ("b3", "b12"...), a single bit is read as a boolean
+        if size == 1 {
+            PureType::Boolean
         } else {
-            PureType::AnyType // Placeholder for unexpected cases
+            PureType::BitSizedInteger(size)
         }
     } else {
         match base_type {
@@ -32,19 +41,137 @@ pub fn parse_kaitai_type(type_str: &str) -> Result<Type, io::Error> {
             "s8" => PureType::SignedInteger(8),
             "f4" => PureType::FloatingPoint(4),
             "f8" => PureType::FloatingPoint(8),
-            "bool" | "b1" => PureType::Boolean,
+            "bool" => PureType::Boolean,
             "str" => PureType::String,
             "strz" => PureType::StringZ,
             "struct" => PureType::ArbitraryStruct,
             "io" => PureType::IOStream,
             "any" => PureType::AnyType,
-            // TODO : implement a parsing system for user-defined type
-            _ => PureType::AnyType, // Placeholder for unknown types
+            _ => parse_user_type(base_type)?,
         }
     };
 
     Ok(Type {
         pure_type,
         is_array,
+        endian,
+    })
+}
+
+/// Parses a switch type from its mapping representation (`switch-on` / `cases`)
+pub fn parse_switch_type(switch_value: &Value) -> Result<Type, io::Error> {
+    let switch_on = match switch_value.get("switch-on") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::Bool(b)) => b.to_string(),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid 'switch-on' field. Expected an expression.",
+            ))
+        }
+    };
+
+    let cases_map = match switch_value.get("cases") {
+        Some(Value::Mapping(cases_map)) => cases_map,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid 'cases' field. Expected a mapping of case expressions to types.",
+            ))
+        }
+    };
+
+    let mut cases = Vec::new();
+    let mut default = None;
+    for (case_value, case_type) in cases_map {
+        // Case expressions may be written as YAML numbers, booleans or strings
+        let case_expr = match case_value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid case in 'cases' field. Expected an expression.",
+                ))
+            }
+        };
+
+        let case_type = case_type.as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid type for case '{}'. Expected a string.", case_expr),
+            )
+        })?;
+        let case_type = parse_kaitai_type(case_type)?;
+
+        if case_expr == "_" {
+            default = Some(Box::new(case_type));
+        } else {
+            cases.push((compile_expression("cases", &case_expr)?, case_type));
+        }
+    }
+
+    Ok(Type {
+        pure_type: PureType::Switch(SwitchType {
+            switch_on: compile_expression("switch-on", &switch_on)?,
+            cases,
+            default,
+        }),
+        is_array: false,
+        endian: None,
     })
 }
+
+/// Parses a user-defined type name, possibly scoped ("header", "file::entry")
+fn parse_user_type(type_str: &str) -> Result<PureType, io::Error> {
+    validate_values(&[type_str.to_string()], Config::TYPE_NAME_PATTERN).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid type name '{}'", type_str),
+        )
+    })?;
+
+    // Parametric types ("entry(4)") would be parsed without their arguments, they are rejected instead
+    if type_str.contains('(') {
+        return Err(KaitaiError::unsupported(format!(
+            "Parametric type '{}', the arguments of types are not supported",
+            type_str
+        ))
+        .into());
+    }
+
+    Ok(PureType::UserType(type_str.to_string()))
+}
+
+/// Parses the size of a bit-sized integer type name ("b1" to "b64")
+fn parse_bit_size(type_str: &str) -> Option<u8> {
+    let digits = type_str.strip_prefix('b')?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    digits
+        .parse::<u8>()
+        .ok()
+        .filter(|size| (1..=64).contains(size))
+}
+
+/// Splits a `le`/`be` suffix from a multi-byte numeric or bit-sized type name
+/// Returns the type name unchanged if it has no valid endianness suffix
+fn split_endian_suffix(type_str: &str) -> (&str, Option<EndianEnum>) {
+    let (base_type, endian) = if let Some(base_type) = type_str.strip_suffix("le") {
+        (base_type, EndianEnum::Le)
+    } else if let Some(base_type) = type_str.strip_suffix("be") {
+        (base_type, EndianEnum::Be)
+    } else {
+        return (type_str, None);
+    };
+
+    match base_type {
+        "u2" | "u4" | "u8" | "s2" | "s4" | "s8" | "f4" | "f8" => (base_type, Some(endian)),
+        _ if parse_bit_size(base_type).is_some() => (base_type, Some(endian)),
+        _ => (type_str, None),
+    }
+}
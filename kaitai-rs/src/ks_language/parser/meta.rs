@@ -10,7 +10,7 @@ pub fn parse_meta(meta_instance: &mut Meta, meta: &Value) -> Result<(), io::Erro
     if let Value::Mapping(meta_map) = meta {
         if let Some(id_value) = meta_map.get(&Value::String("id".to_string())) {
             if let Value::String(id_str) = id_value {
-                parse_identifier(&mut meta_instance.identifier, id_str).unwrap();
+                parse_identifier(&mut meta_instance.identifier, id_str)?;
             }
         }
 
@@ -44,6 +44,10 @@ pub fn parse_meta(meta_instance: &mut Meta, meta: &Value) -> Result<(), io::Erro
             parse_endian(meta_instance, endian_value)?;
         }
 
+        if let Some(bit_endian_value) = meta_map.get(&Value::String("bit-endian".to_string())) {
+            parse_bit_endian(meta_instance, bit_endian_value)?;
+        }
+
         if let Some(imports_value) = meta_map.get(&Value::String("imports".to_string())) {
             parse_imports(meta_instance, imports_value)?;
         }
@@ -168,6 +172,31 @@ fn parse_endian(meta_instance: &mut Meta, endian_value: &Value) -> Result<(), io
     }
 }
 
+/// Parses the bit-endian field within the "meta" section
+fn parse_bit_endian(meta_instance: &mut Meta, bit_endian_value: &Value) -> Result<(), io::Error> {
+    if let Value::String(s) = bit_endian_value {
+        match s.to_lowercase().as_str() {
+            "le" => {
+                meta_instance.set_bit_endian(EndianEnum::Le);
+                Ok(())
+            }
+            "be" => {
+                meta_instance.set_bit_endian(EndianEnum::Be);
+                Ok(())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid 'bit-endian' type in meta section",
+            )),
+        }
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid or missing 'bit-endian' field in meta section",
+        ))
+    }
+}
+
 /// Parses the imports field within the "meta" section
 fn parse_imports(meta_instance: &mut Meta, imports_value: &Value) -> Result<(), io::Error> {
     if let Value::Sequence(imports_seq) = imports_value {
@@ -182,8 +211,7 @@ fn parse_imports(meta_instance: &mut Meta, imports_value: &Value) -> Result<(),
             })
             .collect();
 
-        meta_instance.set_imports(values);
-        Ok(())
+        meta_instance.set_imports(values)
     } else {
         Err(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -2,6 +2,7 @@ pub mod attribute;
 pub mod doc;
 pub mod doc_ref;
 pub mod enums;
+pub mod expression;
 pub mod identifier;
 pub mod instances;
 pub mod kaitai_type;
@@ -12,7 +12,7 @@ use std::io;
 pub fn parse_params(params_instance: &mut Params, params: &Value) -> Result<(), io::Error> {
     if let Value::Sequence(sequence) = params {
         for mapping in sequence {
-            parse_paramspec(params_instance, mapping).unwrap();
+            parse_paramspec(params_instance, mapping)?;
         }
     }
     Ok(())
@@ -24,17 +24,12 @@ pub fn parse_paramspec(params_instance: &mut Params, param_spec: &Value) -> Resu
 
     if let Value::Mapping(param_map) = param_spec {
         for (key, value) in param_map {
-            if let Value::String(_str_value) = value {
+            if let Value::String(str_value) = value {
                 match key.as_str() {
-                    Some("id") => {
-                        parse_identifier(&mut param_spec_instance.id, value.as_str().unwrap())
-                            .unwrap();
-                    }
-                    Some("type") => parse_type(&mut param_spec_instance.param_type, value).unwrap(),
-                    Some("doc") => parse_doc(&mut param_spec_instance.doc, value).unwrap(),
-                    Some("doc-ref") => {
-                        parse_doc_ref(&mut param_spec_instance.doc_ref, value).unwrap()
-                    }
+                    Some("id") => parse_identifier(&mut param_spec_instance.id, str_value)?,
+                    Some("type") => parse_type(&mut param_spec_instance.param_type, value)?,
+                    Some("doc") => parse_doc(&mut param_spec_instance.doc, value)?,
+                    Some("doc-ref") => parse_doc_ref(&mut param_spec_instance.doc_ref, value)?,
 
                     // TODO: Find the corresponding enum
                     // Some("enum") => {todo!()}
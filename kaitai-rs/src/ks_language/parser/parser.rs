@@ -1,3 +1,4 @@
+use crate::core::error::KaitaiError;
 use crate::ks_language::language::doc::Doc;
 use crate::ks_language::language::doc_ref::DocRef;
 use crate::ks_language::language::enums::Enums;
@@ -15,7 +16,6 @@ use crate::ks_language::parser::params::parse_params;
 use crate::ks_language::parser::seq::parse_seq;
 use crate::ks_language::parser::types::parse_types;
 use serde_yaml::Value;
-use std::fs::File;
 use std::io::{self, Read};
 
 // KSLanguageParser struct to handle parsing logic
@@ -45,20 +45,12 @@ impl KSLanguageParser {
         }
     }
 
-    pub fn parse_yaml(&mut self, file_path: &str) -> Result<Value, io::Error> {
-        // Open the file
-        let file = File::open(file_path)?;
-
-        // Create a buffered reader to efficiently read the file
-        let mut reader = io::BufReader::new(file);
-
-        // Read the file content into a String
-        let mut content = String::new();
-        reader.read_to_string(&mut content)?;
-
+    /// Parses a Kaitai Struct YAML document from a reader and processes its sections
+    ///
+    /// Returns the parsed YAML document, files are read with `FormatDescription::load_from_file`
+    pub fn parse_yaml<R: Read>(&mut self, reader: R) -> Result<Value, KaitaiError> {
         // Parse the YAML content into serde_yaml::Value
-        let yaml_value: Value = serde_yaml::from_str(&content)
-            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let yaml_value: Value = serde_yaml::from_reader(reader)?;
 
         self.parse_sections(&yaml_value)?;
 
@@ -66,48 +58,57 @@ impl KSLanguageParser {
     }
 
     /// Parses and processes sections from a Kaitai Struct YAML file.
-    pub fn parse_sections(&mut self, yaml_value: &Value) -> Result<(), io::Error> {
+    ///
+    /// Errors are located by the path of the invalid YAML node, e.g. `/seq/0/size`
+    pub fn parse_sections(&mut self, yaml_value: &Value) -> Result<(), KaitaiError> {
         // Match and process each section
         match yaml_value {
             Value::Mapping(map) => {
                 // Process the "meta" section
                 if let Some(meta) = map.get(&Value::String("meta".to_string())) {
-                    parse_meta(&mut self.meta, meta).unwrap();
+                    parse_meta(&mut self.meta, meta)
+                        .map_err(|error| KaitaiError::from(error).in_schema("meta"))?;
                 }
 
                 // Process the "doc" section
                 if let Some(doc) = map.get(&Value::String("doc".to_string())) {
-                    parse_doc(&mut self.doc, doc).unwrap();
+                    parse_doc(&mut self.doc, doc)
+                        .map_err(|error| KaitaiError::from(error).in_schema("doc"))?;
                 }
 
                 // Process the "doc_ref" section
                 if let Some(doc_ref) = map.get(&Value::String("doc-ref".to_string())) {
-                    parse_doc_ref(&mut self.doc_ref, doc_ref).unwrap();
+                    parse_doc_ref(&mut self.doc_ref, doc_ref)
+                        .map_err(|error| KaitaiError::from(error).in_schema("doc-ref"))?;
                 }
 
                 // Process the "params" section
                 if let Some(params) = map.get(&Value::String("params".to_string())) {
-                    parse_params(&mut self.params, params).unwrap();
+                    parse_params(&mut self.params, params)
+                        .map_err(|error| KaitaiError::from(error).in_schema("params"))?;
                 }
 
                 // Process the "seq" section
                 if let Some(seq) = map.get(&Value::String("seq".to_string())) {
-                    parse_seq(&mut self.seq, seq).unwrap();
+                    parse_seq(&mut self.seq, seq).map_err(|error| error.in_schema("seq"))?;
                 }
 
                 // Process the "types" section
                 if let Some(types) = map.get(&Value::String("types".to_string())) {
-                    parse_types(&mut self.types, types).unwrap();
+                    parse_types(&mut self.types, types)
+                        .map_err(|error| error.in_schema("types"))?;
                 }
 
                 // Process the "instances" section
                 if let Some(instances) = map.get(&Value::String("instances".to_string())) {
-                    parse_instances(&mut self.instances, instances).unwrap();
+                    parse_instances(&mut self.instances, instances)
+                        .map_err(|error| error.in_schema("instances"))?;
                 }
 
                 // Process the "enums" section
                 if let Some(enums) = map.get(&Value::String("enums".to_string())) {
-                    parse_enums(&mut self.enums, enums).unwrap();
+                    parse_enums(&mut self.enums, enums)
+                        .map_err(|error| KaitaiError::from(error).in_schema("enums"))?;
                 }
             }
             _ => {
@@ -115,7 +116,8 @@ impl KSLanguageParser {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidData,
                     "Unexpected YAML structure",
-                ));
+                )
+                .into());
             }
         }
 
@@ -1,13 +1,14 @@
+use crate::core::error::KaitaiError;
 use crate::ks_language::language::seq::Seq;
 use crate::ks_language::parser::attribute::parse_attribute;
 use serde_yaml::Value;
-use std::io;
 
 /// Parses the "seq" section of the Kaitai Struct definition
-pub fn parse_seq(seq_instance: &mut Seq, seq: &Value) -> Result<(), io::Error> {
+pub fn parse_seq(seq_instance: &mut Seq, seq: &Value) -> Result<(), KaitaiError> {
     if let Value::Sequence(sequence) = seq {
-        for attribute in sequence {
-            let parsed_attribute = parse_attribute(attribute)?;
+        for (index, attribute) in sequence.iter().enumerate() {
+            let parsed_attribute =
+                parse_attribute(attribute).map_err(|error| error.in_schema(&index.to_string()))?;
             seq_instance.add_attribute(parsed_attribute);
         }
     }
@@ -1,7 +1,9 @@
+use crate::core::error::KaitaiError;
 use crate::ks_language::language::doc::Doc;
 use crate::ks_language::language::doc_ref::DocRef;
 use crate::ks_language::language::enums::Enums;
 use crate::ks_language::language::identifier::Identifier;
+use crate::ks_language::language::instances::Instances;
 use crate::ks_language::language::meta::Meta;
 use crate::ks_language::language::params::Params;
 use crate::ks_language::language::seq::Seq;
@@ -9,13 +11,16 @@ use crate::ks_language::language::types::TypeSpec;
 use crate::ks_language::language::types::Types;
 use crate::ks_language::parser::doc::parse_doc;
 use crate::ks_language::parser::doc_ref::parse_doc_ref;
+use crate::ks_language::parser::enums::parse_enums;
+use crate::ks_language::parser::instances::parse_instances;
 use crate::ks_language::parser::meta::parse_meta;
 use crate::ks_language::parser::params::parse_params;
+use crate::ks_language::parser::seq::parse_seq;
 use serde_yaml::Value;
 use std::io;
 
 /// Parses the "types" section of the Kaitai Struct definition.
-pub fn parse_types(types_instance: &mut Types, types_section: &Value) -> Result<(), io::Error> {
+pub fn parse_types(types_instance: &mut Types, types_section: &Value) -> Result<(), KaitaiError> {
     match types_section {
         Value::Mapping(types_map) => {
             // Iterate over each entry in the types map and parse individual types
@@ -28,7 +33,8 @@ pub fn parse_types(types_instance: &mut Types, types_section: &Value) -> Result<
         _ => Err(io::Error::new(
             io::ErrorKind::InvalidData,
             "Unexpected YAML structure in types section",
-        )),
+        )
+        .into()),
     }
 }
 
@@ -37,14 +43,20 @@ fn parse_typespec(
     types_instance: &mut Types,
     typespec_name: &Value,
     typespec_values: &Value,
-) -> Result<(), io::Error> {
+) -> Result<(), KaitaiError> {
     // If "type_values" is a mapping, proceed with parsing
     if let Value::Mapping(variant_map) = typespec_values {
         // Create an Identifier for the TypeSpec
         let mut typespec_identifier = Identifier::new();
+        let typespec_name = typespec_name.as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid type name in types section. Expected a string.",
+            )
+        })?;
 
         typespec_identifier
-            .from_string_vec(vec![typespec_name.as_str().unwrap().to_string()])
+            .from_string_vec(vec![typespec_name.to_string()])
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
 
         // Create an TypeSpec instance to store parsed variant values
@@ -54,32 +66,36 @@ fn parse_typespec(
             Seq::new(),
             Types::new(),
             Enums::new(),
-            String::new(),
+            Instances::new(),
             Doc::new(),
             DocRef::new(),
         );
 
         for (key, value) in variant_map {
-            if let Value::String(_str_value) = value {
-                match key.as_str() {
-                    Some("meta") => parse_meta(&mut typespec_instance.meta, value).unwrap(),
-                    Some("types") => parse_types(&mut typespec_instance.type_types, value).unwrap(),
-                    Some("params") => parse_params(&mut typespec_instance.params, value).unwrap(),
-                    Some("doc") => parse_doc(&mut typespec_instance.doc, value).unwrap(),
-                    Some("doc-ref") => {
-                        parse_doc_ref(&mut typespec_instance.doc_ref, value).unwrap()
-                    }
-
-                    // TODO: Parse the enum / seq / instances fields
-                    // Some("enum") => {todo!()}
-                    // Some("seq") => {todo!()}
-                    // Some("instances") => {todo!()}
-                    _ => (),
+            let key = key.as_str().unwrap_or_default();
+            match key {
+                "meta" => parse_meta(&mut typespec_instance.meta, value).map_err(KaitaiError::from),
+                "types" => parse_types(&mut typespec_instance.type_types, value),
+                "params" => {
+                    parse_params(&mut typespec_instance.params, value).map_err(KaitaiError::from)
+                }
+                "doc" => parse_doc(&mut typespec_instance.doc, value).map_err(KaitaiError::from),
+                "doc-ref" => {
+                    parse_doc_ref(&mut typespec_instance.doc_ref, value).map_err(KaitaiError::from)
+                }
+                "seq" => parse_seq(&mut typespec_instance.seq, value),
+                "instances" => parse_instances(&mut typespec_instance.instances, value),
+                "enums" => {
+                    parse_enums(&mut typespec_instance.type_enums, value).map_err(KaitaiError::from)
                 }
+                _ => Ok(()),
             }
+            .map_err(|error| error.in_schema(key).in_schema(typespec_name))?;
         }
 
-        types_instance.add_typespec(typespec_identifier, typespec_instance)?;
+        types_instance
+            .add_typespec(typespec_identifier, typespec_instance)
+            .map_err(|error| KaitaiError::from(error).in_schema(typespec_name))?;
 
         Ok(())
     } else {
@@ -87,6 +103,7 @@ fn parse_typespec(
         Err(io::Error::new(
             io::ErrorKind::InvalidData,
             "Unexpected YAML structure in type section",
-        ))
+        )
+        .into())
     }
 }
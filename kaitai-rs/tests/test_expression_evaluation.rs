@@ -0,0 +1,265 @@
+use kaitai_rs::core::ast::Node;
+use kaitai_rs::core::ast::NodeType;
+use kaitai_rs::core::ast::AST;
+use kaitai_rs::core::error::ExpressionError;
+use kaitai_rs::core::expression::evaluate;
+use kaitai_rs::core::value::Value;
+
+// This file contains unit tests for the evaluation of kaitai expression language expressions.
+// https://doc.kaitai.io/user_guide.html#_expression_language
+
+// Evaluates an expression against an empty AST, panicking if the evaluation fails
+fn eval(expr: &str) -> Value {
+    evaluate(&AST::new(), expr)
+        .unwrap_or_else(|error| panic!("Failed to evaluate '{}': {}", expr, error))
+}
+
+#[test]
+// Test the precedence and the associativity of the operators
+fn test_evaluate_operator_precedence() {
+    let expected = [
+        ("1 + 2 * 3", 7),
+        ("(1 + 2) * 3", 9),
+        ("10 - 4 - 3", 3),
+        ("1 << 2 + 1", 8),
+        ("0xf0 | 0x0f & 0x3c", 0xfc),
+        ("0xff ^ 0x0f | 0x100", 0x1f0),
+        ("-2 * -3", 6),
+        ("~0", -1),
+        ("2 * 3 % 4", 2),
+    ];
+
+    for (expr, value) in expected {
+        assert_eq!(eval(expr), Value::Signed(value), "{}", expr);
+    }
+
+    assert_eq!(eval("6 & 3 == 2"), Value::Bool(true));
+    assert_eq!(eval("not 1 > 2 and true"), Value::Bool(true));
+    assert_eq!(eval("false or 1 < 2 and 2 < 1"), Value::Bool(false));
+}
+
+#[test]
+// Test the floor division and the non-negative modulo of integers
+fn test_evaluate_division_and_modulo() {
+    let expected = [
+        ("7 / 2", 3),
+        ("-7 / 2", -4),
+        ("7 / -2", -4),
+        ("-8 / 2", -4),
+        ("7 % 3", 1),
+        ("-7 % 3", 2),
+        ("-9 % 3", 0),
+    ];
+
+    for (expr, value) in expected {
+        assert_eq!(eval(expr), Value::Signed(value), "{}", expr);
+    }
+
+    assert_eq!(eval("7.0 / 2"), Value::Float(3.5));
+    assert_eq!(eval("-7.5 % 2"), Value::Float(0.5));
+}
+
+#[test]
+// Test the evaluation of literals, comparisons and ternary expressions
+fn test_evaluate_values() {
+    assert_eq!(eval("0x7fff_ffff_ffff_ffff"), Value::Signed(i64::MAX));
+    assert_eq!(eval("0xffff_ffff_ffff_ffff"), Value::Unsigned(u64::MAX));
+    assert_eq!(eval("1.5e2"), Value::Float(150.0));
+    assert_eq!(eval("'ab' + \"c\\n\""), Value::String("abc\n".to_string()));
+    assert_eq!(eval("[1, 2, 3][1]"), Value::Signed(2));
+
+    assert_eq!(eval("'abc' < 'abd'"), Value::Bool(true));
+    assert_eq!(eval("1 == 1.0"), Value::Bool(true));
+    assert_eq!(eval("0xffff_ffff_ffff_ffff > -1"), Value::Bool(true));
+    assert_eq!(eval("[1, 2] != [1, 2]"), Value::Bool(false));
+
+    assert_eq!(
+        eval("2 > 1 ? 'yes' : 'no'"),
+        Value::String("yes".to_string())
+    );
+    assert_eq!(eval("true ? false ? 1 : 2 : 3"), Value::Signed(2));
+
+    // The right operand of a logical operator is not evaluated if not needed
+    assert_eq!(eval("false and missing"), Value::Bool(false));
+    assert_eq!(eval("true or missing"), Value::Bool(true));
+}
+
+#[test]
+// Test evaluating expressions referring to the nodes of the AST
+fn test_evaluate_identifiers() {
+    let ast = AST::new();
+    let len = Node::new(Some("len".to_string()));
+    len.borrow_mut().set_data(vec![0x10, 0x00]);
+    len.borrow_mut().set_node_type(NodeType::Integer);
+    ast.get_root().borrow_mut().add_child(len);
+
+    let magic = Node::new(Some("magic".to_string()));
+    magic.borrow_mut().set_data(vec![0x50, 0x4b]);
+    magic.borrow_mut().set_node_type(NodeType::Array);
+    ast.get_root().borrow_mut().add_child(magic);
+
+    assert_eq!(evaluate(&ast, "len * 2 + 1"), Ok(Value::Signed(33)));
+    assert_eq!(
+        evaluate(&ast, "magic == [0x50, 0x4b]"),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(evaluate(&ast, "magic[1]"), Ok(Value::Unsigned(0x4b)));
+}
+
+#[test]
+// Test indexing a list of the AST and getting its size, which only read the elements they need
+fn test_evaluate_list_elements() {
+    let ast = AST::new();
+    let entries = Node::new(Some("entries".to_string()));
+    entries.borrow_mut().set_node_type(NodeType::List);
+    for data in [vec![0x01], vec![0x02]] {
+        let entry = Node::new(None);
+        entry.borrow_mut().set_data(data);
+        entry.borrow_mut().set_node_type(NodeType::Integer);
+        entries.borrow_mut().add_child(entry);
+    }
+    // An element without a value cannot be converted, the other elements remain usable
+    let absent = Node::new(None);
+    absent.borrow_mut().set_node_type(NodeType::Absent);
+    entries.borrow_mut().add_child(absent);
+    ast.get_root().borrow_mut().add_child(entries);
+
+    assert_eq!(evaluate(&ast, "entries[1]"), Ok(Value::Unsigned(2)));
+    assert_eq!(evaluate(&ast, "entries.size"), Ok(Value::Signed(3)));
+    assert_eq!(
+        evaluate(&ast, "entries[3]"),
+        Err(ExpressionError::IndexOutOfBounds {
+            index: 3,
+            length: 3
+        })
+    );
+    assert!(matches!(
+        evaluate(&ast, "entries[2]"),
+        Err(ExpressionError::Absent(_))
+    ));
+    assert!(matches!(
+        evaluate(&ast, "entries == [1, 2]"),
+        Err(ExpressionError::Absent(_))
+    ));
+}
+
+#[test]
+// Test the errors raised by invalid expressions
+fn test_evaluate_errors() {
+    let ast = AST::new();
+
+    assert!(matches!(
+        evaluate(&ast, "1 +"),
+        Err(ExpressionError::Syntax { .. })
+    ));
+    assert!(matches!(
+        evaluate(&ast, "a b"),
+        Err(ExpressionError::Syntax { .. })
+    ));
+    assert_eq!(
+        evaluate(&ast, "missing + 1"),
+        Err(ExpressionError::UnknownName("missing".to_string()))
+    );
+    assert_eq!(
+        evaluate(&ast, "1 / 0"),
+        Err(ExpressionError::DivisionByZero)
+    );
+    assert_eq!(
+        evaluate(&ast, "0xffff_ffff_ffff_ffff + 1"),
+        Err(ExpressionError::Overflow)
+    );
+    assert_eq!(
+        evaluate(&ast, "[1, 2][2]"),
+        Err(ExpressionError::IndexOutOfBounds {
+            index: 2,
+            length: 2
+        })
+    );
+    assert!(matches!(
+        evaluate(&ast, "1 + 'a'"),
+        Err(ExpressionError::TypeMismatch(_))
+    ));
+    assert!(matches!(
+        evaluate(&ast, "1 and true"),
+        Err(ExpressionError::TypeMismatch(_))
+    ));
+}
+
+#[test]
+// Test the built-in methods of integers, floats, booleans, strings and arrays
+fn test_evaluate_methods() {
+    assert_eq!(eval("42.to_s"), Value::String("42".to_string()));
+    assert_eq!(eval("7.9.to_i"), Value::Signed(7));
+    assert_eq!(eval("true.to_i + 1"), Value::Signed(2));
+
+    assert_eq!(eval("'abc'.length"), Value::Signed(3));
+    assert_eq!(eval("'abc'.reverse"), Value::String("cba".to_string()));
+    assert_eq!(eval("'42'.to_i + 1"), Value::Signed(43));
+    assert_eq!(eval("'ff'.to_i(16)"), Value::Signed(255));
+    assert_eq!(
+        eval("'hello'.substring(1, 3)"),
+        Value::String("el".to_string())
+    );
+
+    assert_eq!(eval("[3, 1, 2].first"), Value::Signed(3));
+    assert_eq!(eval("[3, 1, 2].last"), Value::Signed(2));
+    assert_eq!(eval("[3, 1, 2].size"), Value::Signed(3));
+    assert_eq!(eval("[3, 1, 2].min"), Value::Signed(1));
+    assert_eq!(eval("[3, 1, 2].max"), Value::Signed(3));
+
+    assert_eq!(eval("(1 + 2).as<u4>"), Value::Signed(3));
+    assert_eq!(eval("1.as<f8>"), Value::Float(1.0));
+}
+
+#[test]
+// Test the methods of byte arrays read from the AST
+fn test_evaluate_bytes_methods() {
+    let ast = AST::new();
+    let magic = Node::new(Some("magic".to_string()));
+    magic.borrow_mut().set_data(vec![0x50, 0x4b]);
+    magic.borrow_mut().set_node_type(NodeType::Array);
+    ast.get_root().borrow_mut().add_child(magic);
+
+    assert_eq!(evaluate(&ast, "magic.length"), Ok(Value::Signed(2)));
+    assert_eq!(
+        evaluate(&ast, "magic.to_s('ASCII')"),
+        Ok(Value::String("PK".to_string()))
+    );
+    assert_eq!(evaluate(&ast, "magic.first"), Ok(Value::Unsigned(0x50)));
+    assert_eq!(evaluate(&ast, "magic.max"), Ok(Value::Unsigned(0x50)));
+}
+
+#[test]
+// Test the errors raised by invalid method calls and casts
+fn test_evaluate_methods_errors() {
+    let ast = AST::new();
+
+    assert_eq!(
+        evaluate(&ast, "'abc'.substring(2, 5)"),
+        Err(ExpressionError::IndexOutOfBounds {
+            index: 5,
+            length: 3
+        })
+    );
+    assert!(matches!(
+        evaluate(&ast, "'x'.to_i"),
+        Err(ExpressionError::TypeMismatch(_))
+    ));
+    assert!(matches!(
+        evaluate(&ast, "'a'.as<u4>"),
+        Err(ExpressionError::TypeMismatch(_))
+    ));
+    assert!(matches!(
+        evaluate(&ast, "1.length"),
+        Err(ExpressionError::Unsupported(_))
+    ));
+    // There is no repetition nor format outside of a parsing
+    assert_eq!(
+        evaluate(&ast, "_index"),
+        Err(ExpressionError::UnknownName("_index".to_string()))
+    );
+    assert!(matches!(
+        evaluate(&ast, "sizeof<u4>"),
+        Err(ExpressionError::Unsupported(_))
+    ));
+}
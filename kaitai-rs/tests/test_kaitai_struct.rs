@@ -0,0 +1,1933 @@
+use kaitai_rs::core::ast::NodeRef;
+use kaitai_rs::core::ast::NodeType;
+use kaitai_rs::core::ast::AST;
+use kaitai_rs::core::error::{EndOfStreamError, KaitaiError};
+use kaitai_rs::core::kaitai_struct::KaitaiStruct;
+use kaitai_rs::core::process::Process;
+use kaitai_rs::core::stream::KaitaiStream;
+use kaitai_rs::core::value::Value;
+use kaitai_rs::ks_language::format_description::FormatDescription;
+use kaitai_rs::ks_language::language::meta::EndianEnum;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// This file contains integration tests for the `KaitaiStruct` parsing engine.
+// Each test writes a small format description and a matching binary file to a
+// temporary directory, parses the file and checks the nodes of the resulting AST.
+
+// Writes the format description and the data to temporary files and parses them
+fn try_parse(name: &str, ksy: &str, data: &[u8]) -> Result<AST, KaitaiError> {
+    try_parse_with(name, ksy, data, |_| {})
+}
+
+// Parses the data like `try_parse`, configuring the parser before the parsing
+fn try_parse_with(
+    name: &str,
+    ksy: &str,
+    data: &[u8],
+    configure: impl FnOnce(&mut KaitaiStruct),
+) -> Result<AST, KaitaiError> {
+    let directory =
+        std::env::temp_dir().join(format!("kaitai-rs-test-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let ksy_path: PathBuf = directory.join(format!("{}.ksy", name));
+    let data_path: PathBuf = directory.join(format!("{}.bin", name));
+    fs::write(&ksy_path, ksy).unwrap();
+    fs::write(&data_path, data).unwrap();
+
+    let format_description = FormatDescription::load_from_file(&ksy_path).unwrap();
+    let mut parser = KaitaiStruct::new(format_description);
+    configure(&mut parser);
+    let result = parser.parse_file(&data_path);
+
+    fs::remove_dir_all(&directory).unwrap();
+    result.map(|_| parser.ast)
+}
+
+// Writes the format description to a temporary file and loads it, without parsing any data
+fn try_load(name: &str, ksy: &str) -> Result<FormatDescription, KaitaiError> {
+    let directory =
+        std::env::temp_dir().join(format!("kaitai-rs-test-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let ksy_path: PathBuf = directory.join(format!("{}.ksy", name));
+    fs::write(&ksy_path, ksy).unwrap();
+
+    let result = FormatDescription::load_from_file(&ksy_path);
+    fs::remove_dir_all(&directory).unwrap();
+    result
+}
+
+// Parses the data, panicking if the parsing fails
+fn parse(name: &str, ksy: &str, data: &[u8]) -> AST {
+    try_parse(name, ksy, data).unwrap()
+}
+
+// Gets a direct child of the given node by ID
+fn child(node: &NodeRef, id: &str) -> NodeRef {
+    node.borrow()
+        .get_children()
+        .iter()
+        .find(|child| child.borrow().get_id().as_deref() == Some(id))
+        .cloned()
+        .unwrap_or_else(|| panic!("Expected a child node named '{}'", id))
+}
+
+#[test]
+// Test parsing signed integers of every size with two's complement semantics
+fn test_parse_signed_integers() {
+    let ksy = "
+meta:
+  id: signed_integers
+seq:
+  - id: a
+    type: s1
+  - id: b
+    type: s2
+  - id: c
+    type: s4
+  - id: d
+    type: s8
+  - id: e
+    type: s1
+";
+    let mut data = vec![0xff];
+    data.extend_from_slice(&(-2i16).to_le_bytes());
+    data.extend_from_slice(&(-100000i32).to_le_bytes());
+    data.extend_from_slice(&i64::MIN.to_le_bytes());
+    data.push(0x7f);
+
+    let ast = parse("signed_integers", ksy, &data);
+    let root = ast.get_root();
+
+    let expected = [
+        ("a", -1),
+        ("b", -2),
+        ("c", -100000),
+        ("d", i64::MIN),
+        ("e", 127),
+    ];
+    for (id, value) in expected {
+        let node = child(root, id);
+        assert_eq!(
+            node.borrow().get_node_type(),
+            Some(&NodeType::SignedInteger)
+        );
+        assert_eq!(node.borrow().get_signed_integer(), Some(value));
+    }
+}
+
+#[test]
+// Test parsing integers with an explicit endianness suffix and with the `meta/endian` default
+fn test_parse_integers_endianness() {
+    let ksy = "
+meta:
+  id: endianness
+  endian: be
+seq:
+  - id: default_be
+    type: u4
+  - id: explicit_le
+    type: u4le
+  - id: explicit_be
+    type: u2be
+  - id: signed_le
+    type: s2le
+  - id: signed_default_be
+    type: s8
+";
+    let mut data = vec![0x12, 0x34, 0x56, 0x78];
+    data.extend_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+    data.extend_from_slice(&[0xab, 0xcd]);
+    data.extend_from_slice(&(-300i16).to_le_bytes());
+    data.extend_from_slice(&(-5i64).to_be_bytes());
+
+    let ast = parse("endianness", ksy, &data);
+    let root = ast.get_root();
+
+    let default_be = child(root, "default_be");
+    assert_eq!(default_be.borrow().get_endian(), Some(EndianEnum::Be));
+    assert_eq!(default_be.borrow().get_unsigned_integer(), Some(0x12345678));
+
+    let explicit_le = child(root, "explicit_le");
+    assert_eq!(explicit_le.borrow().get_endian(), Some(EndianEnum::Le));
+    assert_eq!(
+        explicit_le.borrow().get_unsigned_integer(),
+        Some(0x78563412)
+    );
+
+    let explicit_be = child(root, "explicit_be");
+    assert_eq!(explicit_be.borrow().get_unsigned_integer(), Some(0xabcd));
+
+    let signed_le = child(root, "signed_le");
+    assert_eq!(signed_le.borrow().get_signed_integer(), Some(-300));
+
+    let signed_default_be = child(root, "signed_default_be");
+    assert_eq!(signed_default_be.borrow().get_signed_integer(), Some(-5));
+}
+
+#[test]
+// Test parsing single and double precision floating-point numbers
+fn test_parse_floats() {
+    let ksy = "
+meta:
+  id: floats
+  endian: le
+seq:
+  - id: single
+    type: f4
+  - id: double_be
+    type: f8be
+  - id: negative
+    type: f4be
+";
+    let mut data = Vec::new();
+    data.extend_from_slice(&1.5f32.to_le_bytes());
+    data.extend_from_slice(&std::f64::consts::PI.to_be_bytes());
+    data.extend_from_slice(&(-0.25f32).to_be_bytes());
+
+    let ast = parse("floats", ksy, &data);
+    let root = ast.get_root();
+
+    let single = child(root, "single");
+    assert_eq!(single.borrow().get_node_type(), Some(&NodeType::Float));
+    assert_eq!(single.borrow().get_float(), Some(1.5));
+
+    let double_be = child(root, "double_be");
+    assert_eq!(double_be.borrow().get_float(), Some(std::f64::consts::PI));
+
+    let negative = child(root, "negative");
+    assert_eq!(negative.borrow().get_float(), Some(-0.25));
+}
+
+#[test]
+// Test parsing big-endian bit-sized integers and booleans, followed by a byte-aligned field
+fn test_parse_bit_sized_integers() {
+    let ksy = "
+meta:
+  id: bit_fields
+seq:
+  - id: flag
+    type: b1
+  - id: version
+    type: b3
+  - id: length
+    type: b12
+  - id: partial
+    type: b2
+  - id: aligned
+    type: u1
+";
+    let data = [0b1010_1100, 0b0101_0011, 0b1100_0000, 0x42];
+
+    let ast = parse("bit_fields", ksy, &data);
+    let root = ast.get_root();
+
+    let flag = child(root, "flag");
+    assert_eq!(flag.borrow().get_node_type(), Some(&NodeType::Boolean));
+    assert_eq!(flag.borrow().get_unsigned_integer(), Some(1));
+
+    let version = child(root, "version");
+    assert_eq!(version.borrow().get_unsigned_integer(), Some(0b010));
+
+    let length = child(root, "length");
+    assert_eq!(length.borrow().get_node_type(), Some(&NodeType::Integer));
+    assert_eq!(
+        length.borrow().get_unsigned_integer(),
+        Some(0b1100_0101_0011)
+    );
+
+    let partial = child(root, "partial");
+    assert_eq!(partial.borrow().get_unsigned_integer(), Some(0b11));
+
+    // The remaining 6 bits of the third byte are skipped
+    let aligned = child(root, "aligned");
+    assert_eq!(aligned.borrow().get_unsigned_integer(), Some(0x42));
+}
+
+#[test]
+// Test parsing little-endian bit-sized integers using the `meta/bit-endian` default
+fn test_parse_bit_sized_integers_little_endian() {
+    let ksy = "
+meta:
+  id: bit_fields_le
+  bit-endian: le
+seq:
+  - id: low
+    type: b3
+  - id: middle
+    type: b9
+  - id: high
+    type: b4
+";
+    let data = [0b1010_1100, 0b0101_0011];
+
+    let ast = parse("bit_fields_le", ksy, &data);
+    let root = ast.get_root();
+
+    let low = child(root, "low");
+    assert_eq!(low.borrow().get_unsigned_integer(), Some(0b100));
+
+    let middle = child(root, "middle");
+    assert_eq!(middle.borrow().get_unsigned_integer(), Some(0b0_0111_0101));
+
+    let high = child(root, "high");
+    assert_eq!(high.borrow().get_unsigned_integer(), Some(0b0101));
+}
+
+#[test]
+// Test parsing user-defined types into nested subtrees, including scoped type names
+fn test_parse_user_types() {
+    let ksy = "
+meta:
+  id: user_types
+  endian: le
+seq:
+  - id: header
+    type: header
+  - id: entry
+    type: header::entry
+  - id: trailer
+    type: u1
+types:
+  header:
+    seq:
+      - id: magic
+        type: u2
+      - id: first_entry
+        type: entry
+    types:
+      entry:
+        meta:
+          endian: be
+        seq:
+          - id: value
+            type: u2
+          - id: flags
+            type: flags
+  flags:
+    seq:
+      - id: raw
+        type: u1
+";
+    let data = [0x01, 0x02, 0x00, 0x10, 0xaa, 0x00, 0x20, 0xbb, 0xff];
+
+    let ast = parse("user_types", ksy, &data);
+    let root = ast.get_root();
+
+    let header = child(root, "header");
+    assert_eq!(header.borrow().get_node_type(), Some(&NodeType::Struct));
+    assert_eq!(
+        child(&header, "magic").borrow().get_unsigned_integer(),
+        Some(0x0201)
+    );
+
+    // The nested type overrides the endianness of the top-level format
+    let first_entry = child(&header, "first_entry");
+    assert_eq!(
+        child(&first_entry, "value").borrow().get_unsigned_integer(),
+        Some(0x0010)
+    );
+
+    // Types defined in an enclosing scope are resolved from nested types
+    let flags = child(&first_entry, "flags");
+    assert_eq!(
+        child(&flags, "raw").borrow().get_unsigned_integer(),
+        Some(0xaa)
+    );
+
+    let entry = child(root, "entry");
+    assert_eq!(
+        child(&entry, "value").borrow().get_unsigned_integer(),
+        Some(0x0020)
+    );
+
+    let trailer = child(root, "trailer");
+    assert_eq!(trailer.borrow().get_unsigned_integer(), Some(0xff));
+}
+
+#[test]
+// Test selecting the type of an attribute with `switch-on` / `cases`, including the default case
+fn test_parse_switch_on() {
+    let ksy = "
+meta:
+  id: switch_on
+  endian: le
+seq:
+  - id: first_tag
+    type: u1
+  - id: first_body
+    type:
+      switch-on: first_tag
+      cases:
+        1: u2
+        0x02: record
+        _: u1
+  - id: second_tag
+    type: u1
+  - id: second_body
+    type:
+      switch-on: second_tag
+      cases:
+        1: u2
+        0x02: record
+        _: u1
+types:
+  record:
+    seq:
+      - id: value
+        type: u4
+";
+    let data = [0x02, 0x78, 0x56, 0x34, 0x12, 0x07, 0x99];
+
+    let ast = parse("switch_on", ksy, &data);
+    let root = ast.get_root();
+
+    let first_body = child(root, "first_body");
+    assert_eq!(first_body.borrow().get_node_type(), Some(&NodeType::Struct));
+    assert_eq!(
+        child(&first_body, "value").borrow().get_unsigned_integer(),
+        Some(0x12345678)
+    );
+
+    // No case matches the second tag, the default case is used
+    let second_body = child(root, "second_body");
+    assert_eq!(
+        second_body.borrow().get_node_type(),
+        Some(&NodeType::Integer)
+    );
+    assert_eq!(second_body.borrow().get_unsigned_integer(), Some(0x99));
+}
+
+#[test]
+// Test that `contents` are read from the input, so the following fields are read at the right offset
+fn test_parse_contents() {
+    let ksy = "
+meta:
+  id: contents
+seq:
+  - id: magic
+    contents: [0x7f, 'ELF']
+  - id: version
+    type: u1
+";
+    let data = [0x7f, b'E', b'L', b'F', 0x01];
+
+    let ast = parse("contents", ksy, &data);
+    let root = ast.get_root();
+
+    let magic = child(root, "magic");
+    assert_eq!(
+        magic.borrow().get_data(),
+        Some(&vec![0x7f, b'E', b'L', b'F'])
+    );
+
+    let version = child(root, "version");
+    assert_eq!(version.borrow().get_unsigned_integer(), Some(1));
+}
+
+#[test]
+// Test that `contents` not matching the input fail with a contents mismatch error
+fn test_parse_contents_mismatch() {
+    let ksy = "
+meta:
+  id: contents_mismatch
+seq:
+  - id: version
+    type: u1
+  - id: magic
+    contents: 'PK'
+";
+    let data = [0x01, b'P', b'X'];
+
+    let error = try_parse("contents_mismatch", ksy, &data).unwrap_err();
+    let mismatch = match &error {
+        KaitaiError::ContentsMismatch { path, error } => {
+            assert_eq!(path, "magic");
+            error
+        }
+        error => panic!("Expected a contents mismatch error, got {}", error),
+    };
+
+    assert_eq!(mismatch.offset, 1);
+    assert_eq!(mismatch.expected, b"PK".to_vec());
+    assert_eq!(mismatch.actual, b"PX".to_vec());
+}
+
+#[test]
+// Test the `expr` and `eos` repeat modes on integers, byte arrays, strings and user types
+fn test_parse_repeat_expr_and_eos() {
+    let ksy = "
+meta:
+  id: repeats
+  endian: le
+seq:
+  - id: count
+    type: u1
+  - id: values
+    type: u2
+    repeat: expr
+    repeat-expr: count
+  - id: names
+    type: strz
+    repeat: expr
+    repeat-expr: 2
+  - id: tags
+    size: 2
+    repeat: expr
+    repeat-expr: 0
+  - id: records
+    type: record
+    repeat: eos
+types:
+  record:
+    seq:
+      - id: kind
+        type: u1
+      - id: body
+        size: 1
+";
+    let mut data = vec![3, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00];
+    data.extend_from_slice(b"ab\0c\0");
+    data.extend_from_slice(&[0x10, 0xaa, 0x20, 0xbb]);
+
+    let ast = parse("repeats", ksy, &data);
+    let root = ast.get_root();
+
+    let values = child(root, "values");
+    assert_eq!(values.borrow().get_node_type(), Some(&NodeType::List));
+    let values: Vec<u64> = values
+        .borrow()
+        .get_children()
+        .iter()
+        .map(|value| value.borrow().get_unsigned_integer().unwrap())
+        .collect();
+    assert_eq!(values, vec![1, 2, 3]);
+
+    let names = child(root, "names");
+    let names: Vec<Vec<u8>> = names
+        .borrow()
+        .get_children()
+        .iter()
+        .map(|name| name.borrow().get_data().cloned().unwrap())
+        .collect();
+    // The terminators are consumed but not included
+    assert_eq!(names, vec![b"ab".to_vec(), b"c".to_vec()]);
+
+    assert!(child(root, "tags").borrow().get_children().is_empty());
+
+    let records = child(root, "records");
+    let records = records.borrow();
+    assert_eq!(records.get_children().len(), 2);
+    for (record, (kind, body)) in records
+        .get_children()
+        .iter()
+        .zip([(0x10, 0xaa), (0x20, 0xbb)])
+    {
+        assert_eq!(record.borrow().get_id(), &None);
+        assert_eq!(
+            child(record, "kind").borrow().get_unsigned_integer(),
+            Some(kind)
+        );
+        assert_eq!(child(record, "body").borrow().get_data(), Some(&vec![body]));
+    }
+
+    // Elements of bits are repeated up to the end of the stream, although some do not move the position
+    let ksy = "
+meta:
+  id: repeat_eos_bits
+seq:
+  - id: flags
+    type: b1
+    repeat: eos
+";
+    let ast = parse("repeat_eos_bits", ksy, &[0b1010_0000]);
+    assert_eq!(
+        child(ast.get_root(), "flags").borrow().get_children().len(),
+        8
+    );
+
+    // Elements reading nothing fail the parsing instead of being repeated forever
+    let ksy = "
+meta:
+  id: repeat_eos_empty
+seq:
+  - id: length
+    type: u1
+  - id: items
+    size: length
+    repeat: eos
+";
+    let error = try_parse("repeat_eos_empty", ksy, &[0, 1, 2]).unwrap_err();
+    assert!(matches!(error, KaitaiError::Validation { .. }), "{}", error);
+    assert_eq!(error.path(), Some("items"));
+}
+
+#[test]
+// Test the `until` repeat mode, with `_` referring to the element just read
+fn test_parse_repeat_until() {
+    let ksy = "
+meta:
+  id: repeat_until
+seq:
+  - id: bytes
+    type: u1
+    repeat: until
+    repeat-until: _ != 0
+  - id: trailer
+    type: u1
+";
+    let ast = parse("repeat_until", ksy, &[0, 0, 7, 9]);
+    let root = ast.get_root();
+
+    let bytes: Vec<u64> = child(root, "bytes")
+        .borrow()
+        .get_children()
+        .iter()
+        .map(|byte| byte.borrow().get_unsigned_integer().unwrap())
+        .collect();
+    assert_eq!(bytes, vec![0, 0, 7]);
+    assert_eq!(
+        child(root, "trailer").borrow().get_unsigned_integer(),
+        Some(9)
+    );
+
+    // The end of the stream is reached before the condition holds
+    assert!(try_parse("repeat_until_eos", ksy, &[0, 0]).is_err());
+}
+
+#[test]
+// Test skipping conditional attributes whose `if` expression is false
+fn test_parse_if() {
+    let ksy = "
+meta:
+  id: conditional
+seq:
+  - id: has_extra
+    type: u1
+  - id: has_name
+    type: u1
+  - id: extra
+    type: u2
+    if: has_extra != 0
+  - id: name
+    type: strz
+    if: has_name != 0
+  - id: trailer
+    type: u1
+";
+    let ast = parse("conditional", ksy, &[1, 0, 0x34, 0x12, 0xff]);
+    let root = ast.get_root();
+
+    assert_eq!(
+        child(root, "extra").borrow().get_unsigned_integer(),
+        Some(0x1234)
+    );
+
+    let name = child(root, "name");
+    assert_eq!(name.borrow().get_node_type(), Some(&NodeType::Absent));
+    assert_eq!(name.borrow().get_data(), None);
+
+    assert_eq!(
+        child(root, "trailer").borrow().get_unsigned_integer(),
+        Some(0xff)
+    );
+}
+
+#[test]
+// Test sizes given by expressions and user types parsed from bounded substreams
+fn test_parse_size_substreams() {
+    let ksy = "
+meta:
+  id: substreams
+seq:
+  - id: len_body
+    type: u1
+  - id: body
+    size: len_body
+    type: chunk
+  - id: len_raw
+    type: u1
+  - id: raw
+    size: len_raw
+  - id: rest
+    size-eos: true
+types:
+  chunk:
+    seq:
+      - id: kind
+        type: u1
+      - id: payload
+        size-eos: true
+";
+    let data = [4, 0x01, 0xaa, 0xbb, 0xcc, 2, 0x10, 0x20, 0x30];
+    let ast = parse("substreams", ksy, &data);
+    let root = ast.get_root();
+
+    // The payload stops at the end of the body, not at the end of the file
+    let body = child(root, "body");
+    assert_eq!(
+        child(&body, "kind").borrow().get_unsigned_integer(),
+        Some(1)
+    );
+    assert_eq!(
+        child(&body, "payload").borrow().get_data(),
+        Some(&vec![0xaa, 0xbb, 0xcc])
+    );
+
+    assert_eq!(
+        child(root, "raw").borrow().get_data(),
+        Some(&vec![0x10, 0x20])
+    );
+    assert_eq!(child(root, "rest").borrow().get_data(), Some(&vec![0x30]));
+
+    // A user type cannot read past its substream, even if the file has more data
+    let ksy = "
+meta:
+  id: substream_overflow
+seq:
+  - id: body
+    size: 1
+    type: pair
+  - id: trailer
+    type: u1
+types:
+  pair:
+    seq:
+      - id: value
+        type: u2
+";
+    let error = try_parse("substream_overflow", ksy, &[0x01, 0x02, 0x03]).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::EndOfStream { .. }),
+        "{}",
+        error
+    );
+}
+
+#[test]
+// Test resolving names from the current struct, `_parent`, `_root`, paths and indexes
+fn test_parse_expression_paths() {
+    let ksy = "
+meta:
+  id: paths
+seq:
+  - id: len
+    type: u1
+  - id: header
+    type: header
+  - id: entries
+    type: entry
+    repeat: expr
+    repeat-expr: header.count
+  - id: tail
+    size: entries[1].len + header.count
+types:
+  header:
+    seq:
+      - id: len
+        type: u1
+      - id: count
+        type: u1
+      - id: tag
+        size: len
+      - id: inner
+        type: inner
+    types:
+      inner:
+        seq:
+          - id: len
+            size: _parent._parent.len
+  entry:
+    seq:
+      - id: len
+        type: u1
+      - id: body
+        size: len
+      - id: parent_len
+        size: _parent.len
+      - id: root_count
+        size: _root.header.count - 1
+";
+    let mut data = vec![2];
+    // header: len, count, tag, inner.len
+    data.extend_from_slice(&[3, 2, 0xa1, 0xa2, 0xa3, 0xb1, 0xb2]);
+    // entries: len, body, parent_len, root_count
+    data.extend_from_slice(&[1, 0xc1, 0xd1, 0xd2, 0xe1]);
+    data.extend_from_slice(&[2, 0xc1, 0xc2, 0xd1, 0xd2, 0xe1]);
+    // tail: entries[1].len + header.count
+    data.extend_from_slice(&[0xf1, 0xf2, 0xf3, 0xf4]);
+
+    let ast = parse("paths", ksy, &data);
+    let root = ast.get_root();
+
+    let header = child(root, "header");
+    assert_eq!(
+        child(&header, "tag").borrow().get_data(),
+        Some(&vec![0xa1, 0xa2, 0xa3])
+    );
+    assert_eq!(
+        child(&child(&header, "inner"), "len").borrow().get_data(),
+        Some(&vec![0xb1, 0xb2])
+    );
+
+    let entries = child(root, "entries");
+    let entries = entries.borrow();
+    assert_eq!(entries.get_children().len(), 2);
+    let second = &entries.get_children()[1];
+    assert_eq!(
+        child(second, "body").borrow().get_data(),
+        Some(&vec![0xc1, 0xc2])
+    );
+    assert_eq!(
+        child(second, "parent_len").borrow().get_data(),
+        Some(&vec![0xd1, 0xd2])
+    );
+    assert_eq!(
+        child(second, "root_count").borrow().get_data(),
+        Some(&vec![0xe1])
+    );
+
+    assert_eq!(
+        child(root, "tail").borrow().get_data(),
+        Some(&vec![0xf1, 0xf2, 0xf3, 0xf4])
+    );
+}
+
+#[test]
+// Test built-in methods, stream properties, `_index` and sizes of types and attributes in expressions
+fn test_parse_expression_builtins() {
+    let ksy = "
+meta:
+  id: builtins
+seq:
+  - id: header
+    type: header
+  - id: name
+    type: strz
+  - id: name_copy
+    size: name.length
+  - id: blocks
+    size: _index + 1
+    repeat: expr
+    repeat-expr: sizeof<header> - 1
+  - id: header_copy
+    size: header._sizeof + blocks._sizeof - 3
+  - id: rest
+    size: _io.size - _io.pos - 1
+  - id: last
+    type: u1
+types:
+  header:
+    seq:
+      - id: version
+        type: u2
+      - id: major
+        type: b4
+      - id: minor
+        type: b4
+";
+    let mut data = vec![0x01, 0x00, 0x12];
+    data.extend_from_slice(b"ab\0");
+    data.extend_from_slice(b"cd");
+    // blocks: 2 blocks of 1 and 2 bytes
+    data.extend_from_slice(&[0xb1, 0xb2, 0xb3]);
+    // header_copy: 3 + 3 - 3 bytes
+    data.extend_from_slice(&[0xc1, 0xc2, 0xc3]);
+    // rest and last
+    data.extend_from_slice(&[0xd1, 0xd2, 0xe1]);
+
+    let ast = parse("builtins", ksy, &data);
+    let root = ast.get_root();
+
+    assert_eq!(
+        child(root, "name_copy").borrow().get_data(),
+        Some(&b"cd".to_vec())
+    );
+
+    let blocks = child(root, "blocks");
+    let blocks = blocks.borrow();
+    assert_eq!(blocks.get_children().len(), 2);
+    assert_eq!(
+        blocks.get_children()[0].borrow().get_data(),
+        Some(&vec![0xb1])
+    );
+    assert_eq!(
+        blocks.get_children()[1].borrow().get_data(),
+        Some(&vec![0xb2, 0xb3])
+    );
+
+    assert_eq!(
+        child(root, "header_copy").borrow().get_data(),
+        Some(&vec![0xc1, 0xc2, 0xc3])
+    );
+    assert_eq!(
+        child(root, "rest").borrow().get_data(),
+        Some(&vec![0xd1, 0xd2])
+    );
+    assert_eq!(child(root, "last").borrow().get_data(), Some(&vec![0xe1]));
+}
+
+#[test]
+// Test that expression syntax errors are reported when the format description is loaded, before any parsing
+fn test_load_expression_syntax_error() {
+    let ksy = "
+meta:
+  id: syntax_error
+seq:
+  - id: len
+    type: u1
+  - id: body
+    size: len +
+";
+    let error = try_load("syntax_error", ksy).unwrap_err();
+    assert!(matches!(error, KaitaiError::Schema { .. }), "{}", error);
+    assert_eq!(error.path(), Some("/seq/1/size"));
+}
+
+#[test]
+// Test enum attributes, resolved from the current type or an enclosing one, and enum literals in expressions
+fn test_parse_enums() {
+    let ksy = "
+meta:
+  id: enums
+  endian: le
+seq:
+  - id: animal
+    type: u1
+    enum: animal
+  - id: unknown
+    type: u1
+    enum: animal
+  - id: level
+    type: s2
+    enum: level
+  - id: big
+    type: u8
+    enum: big
+  - id: body
+    type: body
+  - id: dog_extra
+    size: 1
+    if: animal == animal::dog
+  - id: cat_extra
+    size: 1
+    if: animal == animal::cat
+types:
+  body:
+    seq:
+      - id: kind
+        type: u1
+        enum: kind
+      - id: outer
+        type: u1
+        enum: animal
+      - id: payload
+        size: kind.to_i
+    enums:
+      kind:
+        1: small
+        2:
+          id: large
+          doc: A large body
+enums:
+  animal:
+    1: cat
+    2: dog
+  level:
+    -1: low
+    1: high
+  big:
+    0xffffffffffffffff: max
+";
+    let mut data = vec![2, 7, 0xff, 0xff];
+    data.extend_from_slice(&[0xff; 8]);
+    // body: kind, outer, payload
+    data.extend_from_slice(&[2, 1, 0xa1, 0xa2]);
+    // dog_extra
+    data.push(0xb1);
+
+    let ast = parse("enums", ksy, &data);
+    let root = ast.get_root();
+
+    let member = |node: &NodeRef| {
+        let node = node.borrow();
+        let enum_member = node.get_enum_member().expect("Expected an enum value");
+        (enum_member.enum_name.clone(), enum_member.member.clone())
+    };
+
+    assert_eq!(
+        member(&child(root, "animal")),
+        ("animal".to_string(), Some("dog".to_string()))
+    );
+    // Values that are not members of the enum are kept
+    assert_eq!(
+        member(&child(root, "unknown")),
+        ("animal".to_string(), None)
+    );
+    assert_eq!(
+        child(root, "unknown").borrow().get_unsigned_integer(),
+        Some(7)
+    );
+    assert_eq!(
+        member(&child(root, "level")),
+        ("level".to_string(), Some("low".to_string()))
+    );
+    assert_eq!(
+        member(&child(root, "big")),
+        ("big".to_string(), Some("max".to_string()))
+    );
+
+    let body = child(root, "body");
+    assert_eq!(
+        member(&child(&body, "kind")),
+        ("body::kind".to_string(), Some("large".to_string()))
+    );
+    assert_eq!(
+        member(&child(&body, "outer")),
+        ("animal".to_string(), Some("cat".to_string()))
+    );
+    assert_eq!(
+        child(&body, "payload").borrow().get_data(),
+        Some(&vec![0xa1, 0xa2])
+    );
+
+    assert_eq!(
+        child(root, "dog_extra").borrow().get_data(),
+        Some(&vec![0xb1])
+    );
+    assert_eq!(
+        child(root, "cat_extra").borrow().get_node_type(),
+        Some(&NodeType::Absent)
+    );
+
+    // Enums of the same name defined by different types are different enums
+    let ksy = "
+meta:
+  id: same_enum_names
+seq:
+  - id: a
+    type: a
+  - id: b
+    type: b
+instances:
+  same_enum:
+    value: a.kind == a::kind::x
+  other_enum:
+    value: a.kind == b::kind::y
+types:
+  a:
+    seq:
+      - id: kind
+        type: u1
+        enum: kind
+      - id: extra
+        type: u1
+        if: kind == kind::x
+    enums:
+      kind:
+        1: x
+  b:
+    seq:
+      - id: kind
+        type: u1
+        enum: kind
+    enums:
+      kind:
+        1: y
+";
+    let ast = parse("same_enum_names", ksy, &[1, 0x2a, 1]);
+    let root = ast.get_root();
+    assert_eq!(
+        member(&child(&child(root, "b"), "kind")),
+        ("b::kind".to_string(), Some("y".to_string()))
+    );
+    assert_eq!(
+        child(&child(root, "a"), "extra")
+            .borrow()
+            .get_unsigned_integer(),
+        Some(0x2a)
+    );
+    assert_eq!(
+        child(root, "same_enum").borrow().get_unsigned_integer(),
+        Some(1)
+    );
+    assert_eq!(
+        child(root, "other_enum").borrow().get_unsigned_integer(),
+        Some(0)
+    );
+
+    // Enums must be defined by the current type or an enclosing one
+    let ksy = "
+meta:
+  id: unknown_enum
+seq:
+  - id: animal
+    type: u1
+    enum: animal
+";
+    let error = try_parse("unknown_enum", ksy, &[1]).unwrap_err();
+    assert!(matches!(error, KaitaiError::Schema { .. }), "{}", error);
+}
+
+#[test]
+// Test value instances and positional instances, computed when used by an expression or after the seq
+fn test_parse_instances() {
+    let ksy = "
+meta:
+  id: instances
+  endian: le
+seq:
+  - id: header_len
+    type: u1
+  - id: header
+    size: double_len
+  - id: body
+    type: body
+instances:
+  double_len:
+    value: header_len * 2
+  magic:
+    pos: 0x0a
+    type: u2
+  tail:
+    pos: _io.size - 1
+    size: 1
+  missing:
+    pos: 0
+    type: u1
+    if: header_len == 0
+types:
+  body:
+    seq:
+      - id: payload
+        size: _root.magic - 0x100
+    instances:
+      first:
+        io: _root._io
+        pos: 1
+        type: u1
+      payload_len:
+        value: payload.length
+";
+    // header_len, header, body.payload, padding, magic, tail
+    let data = [
+        2, 0xa1, 0xa2, 0xa3, 0xa4, 0xb1, 0xb2, 0, 0, 0, 0x02, 0x01, 0xff,
+    ];
+
+    let ast = parse("instances", ksy, &data);
+    let root = ast.get_root();
+
+    assert_eq!(
+        child(root, "double_len").borrow().get_signed_integer(),
+        Some(4)
+    );
+    assert_eq!(
+        child(root, "header").borrow().get_data(),
+        Some(&vec![0xa1, 0xa2, 0xa3, 0xa4])
+    );
+    assert_eq!(
+        child(root, "magic").borrow().get_unsigned_integer(),
+        Some(0x102)
+    );
+    // Positional instances do not move the stream of the struct
+    let body = child(root, "body");
+    assert_eq!(
+        child(&body, "payload").borrow().get_data(),
+        Some(&vec![0xb1, 0xb2])
+    );
+    assert_eq!(
+        child(&body, "first").borrow().get_unsigned_integer(),
+        Some(0xa1)
+    );
+    assert_eq!(
+        child(&body, "payload_len").borrow().get_unsigned_integer(),
+        Some(2)
+    );
+    assert_eq!(child(root, "tail").borrow().get_data(), Some(&vec![0xff]));
+    assert_eq!(
+        child(root, "missing").borrow().get_node_type(),
+        Some(&NodeType::Absent)
+    );
+
+    // The `_io` of an instance is the stream of the struct at its current position
+    let ksy = "
+meta:
+  id: instance_position
+seq:
+  - id: first
+    type: u1
+  - id: second
+    type: u1
+    if: p == 1 and next == 0x22
+instances:
+  p:
+    value: _io.pos
+  next:
+    type: u1
+";
+    let ast = parse("instance_position", ksy, &[0x11, 0x22]);
+    let root = ast.get_root();
+    assert_eq!(child(root, "p").borrow().get_signed_integer(), Some(1));
+    assert_eq!(
+        child(root, "next").borrow().get_unsigned_integer(),
+        Some(0x22)
+    );
+    assert_eq!(
+        child(root, "second").borrow().get_unsigned_integer(),
+        Some(0x22)
+    );
+
+    // A positional instance that cannot be read only fails the parsing when it is used
+    let ksy = "
+meta:
+  id: instance_out_of_bounds
+seq:
+  - id: value
+    type: u1
+instances:
+  far:
+    pos: 10
+    type: u1
+";
+    let ast = parse("instance_out_of_bounds", ksy, &[1]);
+    assert!(ast.get_root().borrow().get_child("far").is_none());
+
+    // Errors of the format description in unused instances are reported
+    let broken_instances = [
+        ("unknown_type", "pos: 0\n    type: no_such_type"),
+        ("unknown_name", "value: valeu + 1"),
+        ("type_mismatch", "value: value.to_s + 1"),
+    ];
+    for (name, instance) in broken_instances {
+        let ksy = format!(
+            "
+meta:
+  id: {}
+seq:
+  - id: value
+    type: u1
+instances:
+  broken:
+    {}
+",
+            name, instance
+        );
+        let error = try_parse(name, &ksy, &[1]).unwrap_err();
+        assert_eq!(error.path(), Some("broken"), "{}", error);
+        assert!(
+            !matches!(error, KaitaiError::EndOfStream { .. }),
+            "{}",
+            error
+        );
+    }
+
+    let ksy = "
+meta:
+  id: used_instance_out_of_bounds
+seq:
+  - id: value
+    type: u1
+    if: far == 0
+instances:
+  far:
+    pos: 10
+    type: u1
+";
+    let error = try_parse("used_instance_out_of_bounds", ksy, &[1]).unwrap_err();
+    // The error is the one of the instance, located at the instance rather than at the attribute using it
+    match error {
+        KaitaiError::EndOfStream { path, error } => {
+            assert_eq!(path, "far");
+            assert_eq!(error.offset, 10);
+        }
+        error => panic!("Expected an end of stream error, got {}", error),
+    }
+
+    // Instances of nested structs and of enclosing structs are located from the root
+    let ksy = "
+meta:
+  id: nested_instances_out_of_bounds
+seq:
+  - id: body
+    type: body
+types:
+  body:
+    seq:
+      - id: value
+        type: u1
+        if: far == 0
+      - id: other
+        type: u1
+        if: _root.top == 0
+    instances:
+      far:
+        pos: _io.size * far_factor
+        type: u1
+      far_factor:
+        value: 10
+instances:
+  top:
+    pos: 20
+    type: u1
+";
+    let error = try_parse("nested_instances_out_of_bounds", ksy, &[1]).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::EndOfStream { .. }),
+        "{}",
+        error
+    );
+    assert_eq!(error.path(), Some("body.far"));
+
+    let ksy = ksy.replace("if: far == 0", "if: far_factor == 10");
+    let error = try_parse("enclosing_instance_out_of_bounds", &ksy, &[1, 2]).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::EndOfStream { .. }),
+        "{}",
+        error
+    );
+    assert_eq!(error.path(), Some("top"));
+}
+
+#[test]
+// Test the built-in process routines, their arguments being evaluated as expressions
+fn test_parse_process() {
+    let ksy = "
+meta:
+  id: process
+  endian: le
+seq:
+  - id: key
+    type: u1
+  - id: single_key
+    size: 3
+    process: xor(key)
+  - id: multi_key
+    size: 3
+    process: xor([0x12, 0x34])
+  - id: rotated_left
+    size: 2
+    process: rol(3)
+  - id: rotated_right
+    size: 2
+    process: ror(3)
+  - id: compressed_len
+    type: u1
+  - id: compressed
+    size: compressed_len
+    process: zlib
+    type: body
+  - id: trailer
+    type: u1
+types:
+  body:
+    seq:
+      - id: magic
+        type: u2
+      - id: text_len
+        type: u1
+      - id: text
+        size: text_len
+";
+    let mut data = vec![
+        0x5a, 0x5b, 0x58, 0x59, 0xb3, 0x96, 0xb1, 0x30, 0xe1, 0x81, 0x0f,
+    ];
+    let compressed = [
+        0x78, 0x9c, 0x33, 0x11, 0x62, 0x4e, 0x4c, 0x4a, 0x06, 0x00, 0x03, 0xee, 0x01, 0x70,
+    ];
+    data.push(compressed.len() as u8);
+    data.extend_from_slice(&compressed);
+    data.push(0xee);
+
+    let ast = parse("process", ksy, &data);
+    let root = ast.get_root();
+
+    assert_eq!(
+        child(root, "single_key").borrow().get_data(),
+        Some(&vec![1, 2, 3])
+    );
+    assert_eq!(
+        child(root, "multi_key").borrow().get_data(),
+        Some(&vec![0xa1, 0xa2, 0xa3])
+    );
+    assert_eq!(
+        child(root, "rotated_left").borrow().get_data(),
+        Some(&vec![0x81, 0x0f])
+    );
+    assert_eq!(
+        child(root, "rotated_right").borrow().get_data(),
+        Some(&vec![0x30, 0xe1])
+    );
+
+    // The user type is parsed from the decompressed bytes
+    let compressed = child(root, "compressed");
+    assert_eq!(
+        child(&compressed, "magic").borrow().get_unsigned_integer(),
+        Some(0x1234)
+    );
+    assert_eq!(
+        child(&compressed, "text").borrow().get_data(),
+        Some(&b"abc".to_vec())
+    );
+    // The stream continues after the compressed bytes
+    assert_eq!(
+        child(root, "trailer").borrow().get_unsigned_integer(),
+        Some(0xee)
+    );
+
+    // Invalid routine names are rejected when the format description is loaded
+    let ksy = "
+meta:
+  id: invalid_process
+seq:
+  - id: payload
+    size: 1
+    process: Rot13!
+";
+    let error = try_load("invalid_process", ksy).unwrap_err();
+    assert!(matches!(error, KaitaiError::Schema { .. }), "{}", error);
+
+    // Invalid compressed data is reported as invalid data
+    let ksy = "
+meta:
+  id: invalid_zlib
+seq:
+  - id: payload
+    size: 2
+    process: zlib
+";
+    let error = try_parse("invalid_zlib", ksy, &[1, 2]).unwrap_err();
+    assert!(matches!(error, KaitaiError::Validation { .. }), "{}", error);
+}
+
+// Subtracts the sum of its arguments from every byte
+struct Subtract;
+
+impl Process for Subtract {
+    fn decode(&self, arguments: &[Value], data: &[u8]) -> io::Result<Vec<u8>> {
+        let amount: i128 = arguments
+            .iter()
+            .filter_map(|argument| argument.as_integer())
+            .sum();
+        Ok(data
+            .iter()
+            .map(|byte| byte.wrapping_sub(amount as u8))
+            .collect())
+    }
+}
+
+#[test]
+// Test custom process routines registered on the parser, receiving the evaluated arguments
+fn test_parse_custom_process() {
+    let ksy = "
+meta:
+  id: custom_process
+seq:
+  - id: key
+    type: u1
+  - id: payload
+    size: 3
+    process: my_company.subtract(key, 1)
+    type: body
+types:
+  body:
+    seq:
+      - id: values
+        type: u1
+        repeat: eos
+";
+    let data = [4, 6, 7, 8];
+
+    let ast = try_parse_with("custom_process", ksy, &data, |parser| {
+        parser.register_process("my_company.subtract", Subtract)
+    })
+    .unwrap();
+
+    let payload = child(ast.get_root(), "payload");
+    let values = child(&payload, "values");
+    let values: Vec<_> = values
+        .borrow()
+        .get_children()
+        .iter()
+        .map(|value| value.borrow().get_unsigned_integer())
+        .collect();
+    assert_eq!(values, vec![Some(1), Some(2), Some(3)]);
+
+    // Routines that are not registered are reported when the attribute is parsed
+    let error = try_parse("unregistered_process", ksy, &data).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::Unsupported { .. }),
+        "{}",
+        error
+    );
+    assert!(
+        error.to_string().contains("my_company.subtract"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+// Test decoding `str` and `strz` attributes with their encoding or the `meta/encoding` default
+fn test_parse_strings() {
+    let ksy = "
+meta:
+  id: strings
+  encoding: ASCII
+seq:
+  - id: default
+    type: strz
+  - id: utf8
+    type: str
+    size: 6
+    encoding: UTF-8
+  - id: utf16le
+    type: strz
+    encoding: UTF-16LE
+  - id: utf16be
+    type: str
+    size: 4
+    encoding: UTF-16BE
+  - id: latin1
+    type: str
+    size: 2
+    encoding: ISO-8859-1
+  - id: cp437
+    type: str
+    size: 2
+    encoding: CP437
+  - id: shift_jis
+    type: str
+    size: 4
+    encoding: Shift_JIS
+  - id: padded
+    type: strz
+    size: 4
+instances:
+  utf16le_length:
+    value: utf16le.length
+";
+    let mut data = b"abc\0".to_vec();
+    data.extend_from_slice("h\u{e9}llo".as_bytes());
+    // The null high bytes of the characters are not terminators
+    data.extend_from_slice(&[0x68, 0x00, 0x69, 0x00, 0x00, 0x00]);
+    data.extend_from_slice(&[0x00, 0x6f, 0x00, 0x6b]);
+    data.extend_from_slice(&[0xe9, 0x74]);
+    data.extend_from_slice(&[0x82, 0xb0]);
+    data.extend_from_slice(&[0x93, 0xfa, 0x96, 0x7b]);
+    data.extend_from_slice(b"xy\0\0");
+
+    let ast = parse("strings", ksy, &data);
+    let root = ast.get_root();
+    let string = |id: &str| child(root, id).borrow().get_string().map(str::to_string);
+
+    assert_eq!(string("default"), Some("abc".to_string()));
+    assert_eq!(string("utf8"), Some("h\u{e9}llo".to_string()));
+    assert_eq!(string("utf16le"), Some("hi".to_string()));
+    assert_eq!(string("utf16be"), Some("ok".to_string()));
+    assert_eq!(string("latin1"), Some("\u{e9}t".to_string()));
+    assert_eq!(string("cp437"), Some("\u{e9}\u{2591}".to_string()));
+    assert_eq!(string("shift_jis"), Some("\u{65e5}\u{672c}".to_string()));
+    assert_eq!(string("padded"), Some("xy".to_string()));
+    // The bytes are kept next to the decoded string
+    assert_eq!(
+        child(root, "utf16le").borrow().get_data(),
+        Some(&vec![0x68, 0x00, 0x69, 0x00])
+    );
+    assert_eq!(
+        child(root, "utf16le_length").borrow().get_signed_integer(),
+        Some(2)
+    );
+
+    // Terminators of UTF-16 strings are code units in the byte order of the encoding
+    let ksy = "
+meta:
+  id: utf16_lines
+seq:
+  - id: le
+    type: str
+    terminator: 10
+    encoding: UTF-16LE
+  - id: be
+    type: str
+    terminator: 10
+    encoding: UTF-16BE
+";
+    let data = [0x41, 0x00, 0x0a, 0x00, 0x00, 0x42, 0x00, 0x0a];
+    let ast = parse("utf16_lines", ksy, &data);
+    let root = ast.get_root();
+    assert_eq!(child(root, "le").borrow().get_string(), Some("A"));
+    assert_eq!(child(root, "be").borrow().get_string(), Some("B"));
+
+    // Invalid sequences fail the parsing, unless the strings are decoded lossily
+    let ksy = "
+meta:
+  id: invalid_string
+seq:
+  - id: text
+    type: str
+    size: 2
+    encoding: ASCII
+";
+    let error = try_parse("invalid_string", ksy, &[0x61, 0xff]).unwrap_err();
+    assert!(matches!(error, KaitaiError::Validation { .. }), "{}", error);
+
+    let ast = try_parse_with("lossy_string", ksy, &[0x61, 0xff], |parser| {
+        parser.set_lossy_strings(true)
+    })
+    .unwrap();
+    assert_eq!(
+        child(ast.get_root(), "text").borrow().get_string(),
+        Some("a\u{fffd}")
+    );
+}
+
+#[test]
+// Test the `pad-right`, `terminator`, `include`, `consume` and `eos-error` keys of byte arrays and strings
+fn test_parse_terminators() {
+    let ksy = "
+meta:
+  id: terminators
+  encoding: ASCII
+seq:
+  - id: padded
+    type: str
+    size: 6
+    pad-right: 0x20
+  - id: padded_terminated
+    size: 6
+    pad-right: 0xff
+    terminator: 0x2c
+  - id: included
+    type: str
+    terminator: 0x3b
+    include: true
+  - id: not_consumed
+    terminator: 0x7c
+    consume: false
+  - id: separator
+    type: u1
+  - id: sized_strz
+    type: strz
+    size: 5
+  - id: sized_included
+    size: 3
+    terminator: 0x00
+    include: true
+  - id: unterminated
+    type: str
+    terminator: 0x2e
+    eos-error: false
+";
+    let mut data = b"abc   ".to_vec();
+    data.extend_from_slice(&[0x61, 0x2c, 0x62, 0xff, 0xff, 0xff]);
+    data.extend_from_slice(b"xy;");
+    data.extend_from_slice(b"raw|");
+    data.extend_from_slice(b"hi\0zz");
+    data.extend_from_slice(&[0x01, 0x00, 0x02]);
+    data.extend_from_slice(b"end");
+
+    let ast = parse("terminators", ksy, &data);
+    let root = ast.get_root();
+    let bytes = |id: &str| child(root, id).borrow().get_data().cloned();
+    let string = |id: &str| child(root, id).borrow().get_string().map(str::to_string);
+
+    assert_eq!(string("padded"), Some("abc".to_string()));
+    // The padding is stripped before looking for the terminator
+    assert_eq!(bytes("padded_terminated"), Some(vec![0x61]));
+    assert_eq!(string("included"), Some("xy;".to_string()));
+    assert_eq!(bytes("not_consumed"), Some(b"raw".to_vec()));
+    // The terminator that was not consumed is the next byte read
+    assert_eq!(
+        child(root, "separator").borrow().get_unsigned_integer(),
+        Some(0x7c)
+    );
+    // A sized string ends at its terminator, but the whole size is read
+    assert_eq!(string("sized_strz"), Some("hi".to_string()));
+    assert_eq!(bytes("sized_included"), Some(vec![0x01, 0x00]));
+    // Without `eos-error`, a missing terminator ends the bytes at the end of the stream
+    assert_eq!(string("unterminated"), Some("end".to_string()));
+
+    // With `eos-error`, the default, a missing terminator is an error
+    let ksy = "
+meta:
+  id: missing_terminator
+seq:
+  - id: line
+    terminator: 0x0a
+";
+    let error = try_parse("missing_terminator", ksy, b"no newline").unwrap_err();
+    match error {
+        KaitaiError::EndOfStream { path, error } => {
+            assert_eq!(path, "line");
+            assert_eq!(
+                error,
+                EndOfStreamError {
+                    offset: 0,
+                    requested: None,
+                    remaining: 10,
+                }
+            );
+        }
+        error => panic!("Expected an end of stream error, got {}", error),
+    }
+}
+
+#[test]
+// Test locating parsing errors by the path of the attribute and its offset in its stream
+fn test_error_location() {
+    let ksy = "
+meta:
+  id: error_location
+seq:
+  - id: count
+    type: u1
+  - id: entries
+    type: entry
+    repeat: expr
+    repeat-expr: count
+types:
+  entry:
+    seq:
+      - id: tag
+        type: u1
+      - id: name
+        type: str
+        size: 2
+        encoding: ASCII
+";
+    let data = [0x02, 0x01, b'a', b'b', 0x02, b'c', 0xff];
+
+    let error = try_parse("error_location", ksy, &data).unwrap_err();
+    assert!(matches!(error, KaitaiError::Validation { .. }), "{}", error);
+    assert_eq!(error.path(), Some("entries[1].name"));
+    assert_eq!(error.offset(), Some(5));
+
+    // The stream ends in the middle of the second entry
+    let error = try_parse("error_location_eos", ksy, &data[..5]).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::EndOfStream { .. }),
+        "{}",
+        error
+    );
+    assert_eq!(error.path(), Some("entries[1].name"));
+    assert_eq!(error.offset(), Some(5));
+}
+
+#[test]
+// Test locating errors of the format description by the path of the invalid YAML node
+fn test_schema_error_location() {
+    let ksy = "
+meta:
+  id: schema_error_location
+types:
+  header:
+    seq:
+      - id: kind
+        type: u1
+        repeat: sometimes
+";
+    let error = try_load("schema_error_location", ksy).unwrap_err();
+    assert!(matches!(error, KaitaiError::Schema { .. }), "{}", error);
+    assert_eq!(error.path(), Some("/types/header/seq/0/repeat"));
+
+    // Parametric types are rejected when loading, instead of being parsed without their arguments
+    let ksy = "
+meta:
+  id: parametric_type
+seq:
+  - id: entry
+    type: entry(4)
+types:
+  entry:
+    seq:
+      - id: value
+        type: u1
+";
+    let error = try_load("parametric_type", ksy).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::Unsupported { .. }),
+        "{}",
+        error
+    );
+    assert_eq!(error.path(), Some("/seq/0/type"));
+
+    let error = try_load("invalid_yaml", "meta: [id").unwrap_err();
+    assert!(matches!(error, KaitaiError::Yaml(_)), "{}", error);
+}
+
+#[test]
+// Test reporting truncated data as an end of stream error naming the attribute, instead of panicking
+fn test_parse_truncated_data() {
+    let ksy = "
+meta:
+  id: truncated
+  endian: le
+seq:
+  - id: version
+    type: u1
+  - id: length
+    type: u4
+  - id: body
+    size: length
+  - id: checksum
+    type: u1
+    if: trailer != 0
+instances:
+  trailer:
+    pos: 100
+    type: u2
+";
+    let error = try_parse("truncated", ksy, &[0x01, 0x02, 0x03]).unwrap_err();
+    assert_eq!(error.path(), Some("length"));
+    assert_eq!(error.offset(), Some(1));
+    assert_eq!(
+        error.to_string(),
+        "Unexpected end of stream: requested 4 bytes at offset 1, only 2 remaining (attribute 'length')"
+    );
+
+    // The size read from the data is larger than the data left
+    let error = try_parse("truncated_body", ksy, &[0x01, 0xff, 0, 0, 0, 0x61]).unwrap_err();
+    match error {
+        KaitaiError::EndOfStream { path, error } => {
+            assert_eq!(path, "body");
+            assert_eq!(error.requested, Some(255));
+            assert_eq!(error.remaining, 1);
+            assert_eq!(error.offset, 5);
+        }
+        error => panic!("Expected an end of stream error, got {}", error),
+    }
+
+    // Positional instances may point past the end of the stream, the read then fails when they are used
+    let error = try_parse("truncated_instance", ksy, &[0x01, 0, 0, 0, 0]).unwrap_err();
+    assert_eq!(error.path(), Some("trailer"));
+    assert!(
+        error
+            .to_string()
+            .contains("requested 2 bytes at offset 100, only 0 remaining (attribute 'trailer')"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+// Test parsing from a stream over a `Read + Seek` source, read from the current position of the stream
+fn test_parse_stream() {
+    let ksy = "
+meta:
+  id: from_stream
+seq:
+  - id: length
+    type: u1
+  - id: body
+    type: body
+    size: length
+types:
+  body:
+    seq:
+      - id: value
+        type: u2le
+";
+    let directory =
+        std::env::temp_dir().join(format!("kaitai-rs-test-from_stream-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let ksy_path = directory.join("from_stream.ksy");
+    fs::write(&ksy_path, ksy).unwrap();
+    let format_description = FormatDescription::load_from_file(&ksy_path).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    let mut parser = KaitaiStruct::new(format_description);
+    let mut stream =
+        KaitaiStream::from_reader(io::Cursor::new(vec![0xff, 0x02, 0x34, 0x12])).unwrap();
+    stream.seek(1).unwrap();
+    parser.parse_stream(stream).unwrap();
+
+    let body = child(parser.ast.get_root(), "body");
+    assert_eq!(
+        child(&body, "value").borrow().get_unsigned_integer(),
+        Some(0x1234)
+    );
+}
+
+#[test]
+// Test parsing in-memory data and readers into fresh ASTs, reusing the same parser
+fn test_parse_bytes_and_reader() {
+    let ksy = "
+meta:
+  id: from_memory
+seq:
+  - id: value
+    type: u2be
+";
+    let format_description = try_load("from_memory", ksy).unwrap();
+    let parser = KaitaiStruct::new(format_description);
+
+    let value = |ast: &AST| {
+        child(ast.get_root(), "value")
+            .borrow()
+            .get_unsigned_integer()
+    };
+
+    let first = parser.parse_bytes(&[0x12, 0x34]).unwrap();
+    let second = parser.parse_reader(&[0xab, 0xcd][..]).unwrap();
+    assert_eq!(value(&first), Some(0x1234));
+    assert_eq!(value(&second), Some(0xabcd));
+    assert!(parser.ast.get_root().borrow().get_children().is_empty());
+
+    let error = parser.parse_bytes(&[0x12]).unwrap_err();
+    assert!(
+        matches!(error, KaitaiError::EndOfStream { .. }),
+        "{}",
+        error
+    );
+}
+
+#[test]
+// Test loading format descriptions from YAML strings, readers and JSON, without files
+fn test_load_from_str_reader_and_json() {
+    let ksy = "
+meta:
+  id: in_memory
+  endian: be
+seq:
+  - id: magic
+    contents: [0x4b, 0x53]
+  - id: count
+    type: u2
+";
+    let json = r#"{
+        "meta": { "id": "in_memory", "endian": "be" },
+        "seq": [
+            { "id": "magic", "contents": [75, 83] },
+            { "id": "count", "type": "u2" }
+        ]
+    }"#;
+
+    let format_descriptions = [
+        ksy.parse::<FormatDescription>().unwrap(),
+        FormatDescription::from_reader(ksy.as_bytes()).unwrap(),
+        FormatDescription::from_yaml_value(&serde_yaml::from_str(ksy).unwrap()).unwrap(),
+        FormatDescription::from_json_str(json).unwrap(),
+    ];
+    for format_description in format_descriptions {
+        let ast = KaitaiStruct::new(format_description)
+            .parse_bytes(b"KS\x01\x02")
+            .unwrap();
+        assert_eq!(
+            child(ast.get_root(), "count")
+                .borrow()
+                .get_unsigned_integer(),
+            Some(0x0102)
+        );
+    }
+
+    let error = FormatDescription::from_json_str("{ \"meta\": ").unwrap_err();
+    assert!(matches!(error, KaitaiError::Json(_)), "{}", error);
+
+    let error = "seq: [{ id: count, type: u2, repeat: never }]"
+        .parse::<FormatDescription>()
+        .unwrap_err();
+    assert_eq!(error.path(), Some("/seq/0/repeat"));
+}
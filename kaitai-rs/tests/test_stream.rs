@@ -0,0 +1,200 @@
+use kaitai_rs::core::error::EndOfStreamError;
+use kaitai_rs::core::stream::KaitaiStream;
+use std::cell::Cell;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+// This file contains unit tests for the KaitaiStream struct implemented in the kaitai_rs library.
+// Each test runs the same operations over an in-memory slice and over a `Read + Seek` source,
+// which must behave the same way.
+
+// A reader counting the bytes read from it, to check that a source is only read when needed
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    bytes_read: Rc<Cell<usize>>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buffer)?;
+        self.bytes_read.set(self.bytes_read.get() + count);
+        Ok(count)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+// Creates a stream over a slice and a stream over a reader of the same data
+fn streams(data: &[u8]) -> [KaitaiStream<'_>; 2] {
+    [
+        KaitaiStream::new(data),
+        KaitaiStream::from_reader(Cursor::new(data.to_vec())).unwrap(),
+    ]
+}
+
+#[test]
+// Test reading bytes, positions and the end of the stream
+fn test_stream_read_bytes() {
+    for mut stream in streams(&[1, 2, 3, 4, 5]) {
+        assert_eq!(stream.size(), 5);
+        assert_eq!(stream.read_bytes(2).unwrap(), vec![1, 2]);
+        assert_eq!(stream.pos(), 2);
+        assert_eq!(stream.remaining(), 3);
+
+        stream.seek(4).unwrap();
+        assert_eq!(stream.read_bytes_full().unwrap(), vec![5]);
+        assert!(stream.is_eof());
+
+        stream.seek(1).unwrap();
+        assert_eq!(stream.read_bytes(1).unwrap(), vec![2]);
+    }
+}
+
+#[test]
+// Test reading past the end of the stream, also after seeking past it
+fn test_stream_end_of_stream() {
+    for mut stream in streams(&[1, 2, 3]) {
+        stream.seek(1).unwrap();
+        let error = stream.read_bytes(4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            error
+                .get_ref()
+                .and_then(|error| error.downcast_ref::<EndOfStreamError>()),
+            Some(&EndOfStreamError {
+                offset: 1,
+                requested: Some(4),
+                remaining: 2,
+            })
+        );
+        // A failed read does not move the stream
+        assert_eq!(stream.pos(), 1);
+
+        stream.seek(10).unwrap();
+        assert!(stream.is_eof());
+        assert!(stream.read_bytes(1).is_err());
+        assert_eq!(stream.read_bytes_full().unwrap(), Vec::<u8>::new());
+    }
+}
+
+#[test]
+// Test substreams, which are bounded by their size and positioned relative to their start
+fn test_stream_substream() {
+    for mut stream in streams(&[1, 2, 3, 4, 5]) {
+        stream.read_bytes(1).unwrap();
+        let mut substream = stream.substream(3).unwrap();
+        assert_eq!(stream.pos(), 4);
+
+        assert_eq!(substream.size(), 3);
+        assert_eq!(substream.read_bytes(2).unwrap(), vec![2, 3]);
+        assert!(substream.read_bytes(2).is_err());
+
+        let mut nested = substream.substream(1).unwrap();
+        assert_eq!(nested.read_bytes_full().unwrap(), vec![4]);
+
+        // The parent stream is not moved by its substreams
+        assert_eq!(stream.read_bytes(1).unwrap(), vec![5]);
+        assert!(stream.substream(1).is_err());
+    }
+}
+
+#[test]
+// Test reading bytes up to a terminator, with and without the end of stream error
+fn test_stream_read_bytes_term() {
+    for mut stream in streams(b"ab\0cd") {
+        assert_eq!(
+            stream.read_bytes_term(&[0], false, true, true).unwrap(),
+            b"ab".to_vec()
+        );
+        assert_eq!(stream.pos(), 3);
+
+        let error = stream.read_bytes_term(&[0], false, true, true).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(stream.pos(), 3);
+
+        assert_eq!(
+            stream.read_bytes_term(&[0], false, true, false).unwrap(),
+            b"cd".to_vec()
+        );
+        assert!(stream.is_eof());
+    }
+
+    // Terminators of several bytes are only looked for at multiples of their size
+    for mut stream in streams(&[0x61, 0x00, 0x00, 0x62, 0x00, 0x00]) {
+        assert_eq!(
+            stream.read_bytes_term(&[0, 0], true, true, true).unwrap(),
+            vec![0x61, 0x00, 0x00, 0x62, 0x00, 0x00]
+        );
+    }
+}
+
+#[test]
+// Test reading bit-sized integers over byte boundaries
+fn test_stream_read_bits() {
+    for mut stream in streams(&[0b1010_1100, 0b0101_0011]) {
+        assert_eq!(stream.read_bits_int_be(3).unwrap(), 0b101);
+        assert_eq!(stream.read_bits_int_be(9).unwrap(), 0b0_1100_0101);
+        stream.align_to_byte();
+        assert!(stream.is_eof());
+    }
+
+    // A read past the end of the stream leaves the bits left over unchanged
+    for mut stream in streams(&[0b1010_1100, 0b0101_0011]) {
+        assert_eq!(stream.read_bits_int_be(3).unwrap(), 0b101);
+        assert!(stream.read_bits_int_be(14).is_err());
+        assert_eq!(stream.read_bits_int_be(5).unwrap(), 0b0_1100);
+        assert!(stream.read_bits_int_le(16).is_err());
+        assert_eq!(stream.read_bits_int_le(8).unwrap(), 0b0101_0011);
+    }
+}
+
+#[test]
+// Test that a reader is only read as far as the reads require
+fn test_stream_reads_lazily() {
+    let bytes_read = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(vec![0x2a; 1 << 20]),
+        bytes_read: bytes_read.clone(),
+    };
+    let mut stream = KaitaiStream::from_reader(reader).unwrap();
+    assert_eq!(stream.size(), 1 << 20);
+    assert_eq!(bytes_read.get(), 0);
+
+    stream.read_bytes(4).unwrap();
+    let mut substream = stream.substream(1 << 19).unwrap();
+    substream.seek(1000).unwrap();
+    substream.read_bytes(2).unwrap();
+    stream.seek((1 << 20) - 1).unwrap();
+    stream.read_bytes(1).unwrap();
+    assert_eq!(bytes_read.get(), 7);
+}
+
+#[test]
+// Test reading many short terminated strings, each read only keeping the bytes of its string
+fn test_stream_read_many_terminated() {
+    let data = b"abc\0".repeat(100_000);
+    for mut stream in streams(&data) {
+        while !stream.is_eof() {
+            let bytes = stream.read_bytes_term(&[0], false, true, true).unwrap();
+            assert_eq!(bytes, b"abc".to_vec());
+            assert_eq!(bytes.capacity(), 3);
+        }
+        assert_eq!(stream.pos(), data.len());
+    }
+
+    // The bytes read past a string are not read again from the reader for the next one
+    let bytes_read = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(data.clone()),
+        bytes_read: bytes_read.clone(),
+    };
+    let mut stream = KaitaiStream::from_reader(reader).unwrap();
+    while !stream.is_eof() {
+        stream.read_bytes_term(&[0], false, true, true).unwrap();
+    }
+    assert_eq!(bytes_read.get(), data.len());
+}
@@ -248,14 +248,16 @@ fn test_validate_processes() {
         "xor(123)".to_string(),
         "rol(4)".to_string(),
         "ror(8)".to_string(),
+        "my_company.decrypt(key, 4)".to_string(),
+        "custom".to_string(),
     ];
 
     // Define a set of invalid processes
     let invalid_processes = vec![
-        "invalid".to_string(),
-        "xor(abc)".to_string(),
-        "rol".to_string(),
-        "ror()".to_string(),
+        "Invalid".to_string(),
+        "my-company.decrypt".to_string(),
+        ".xor(abc)".to_string(),
+        "ror(".to_string(),
     ];
 
     // Test validation for valid processes